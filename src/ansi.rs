@@ -0,0 +1,213 @@
+//! Parse the ANSI escape sequences of the input lines
+//!
+//! Commands like `git log --color` or `rg --color=always` print their lines with escape
+//! sequences to color them. With `--ansi` the sequences are removed from the lines, so they
+//! don't get in the way of the matching, but the colors of the SGR sequences (Select Graphic
+//! Rendition, `ESC [ ... m`) are kept to display the lines as they were printed.
+//!
+//! Any other escape sequence (moving the cursor, clearing the line, etc) is just removed.
+
+use ansi_term::{Color, Style};
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// Remove the escape sequences of the string
+///
+/// It returns the plain string and the style changes, with the byte position of the
+/// plain string where every style starts.
+pub fn parse(string: &str) -> (String, Vec<(usize, Style)>) {
+    let mut plain = String::with_capacity(string.len());
+    let mut styles = vec![];
+    let mut style = Style::default();
+    let mut chars = string.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != ESC {
+            plain.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            // Control Sequence Introducer, parameters until the final byte
+            Some('[') => {
+                let mut params = String::new();
+                let mut last = None;
+
+                for ch in chars.by_ref() {
+                    if ('@'..='~').contains(&ch) {
+                        last = Some(ch);
+                        break;
+                    }
+                    params.push(ch);
+                }
+
+                if last == Some('m') {
+                    let next = apply(style, &params);
+
+                    if next != style {
+                        style = next;
+
+                        // Only the last style at the same position matters
+                        if styles.last().map(|(position, _)| *position) == Some(plain.len()) {
+                            styles.pop();
+                        }
+                        styles.push((plain.len(), style));
+                    }
+                }
+            }
+            // Operating System Command, until BEL or String Terminator
+            Some(']') => {
+                while let Some(ch) = chars.next() {
+                    if ch == BEL {
+                        break;
+                    }
+                    if ch == ESC && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Character set designation and the like, intermediate bytes until the final one
+            Some(' '..='/') => {
+                for ch in chars.by_ref() {
+                    if ('0'..='~').contains(&ch) {
+                        break;
+                    }
+                }
+            }
+            // Two characters sequence
+            Some(_) | None => (),
+        }
+    }
+
+    (plain, styles)
+}
+
+/// Style after the given SGR parameters
+fn apply(style: Style, params: &str) -> Style {
+    let codes: Vec<u8> = params
+        .split([';', ':'])
+        .map(|code| code.parse().unwrap_or(0))
+        .collect();
+
+    let mut style = style;
+    let mut codes = codes.into_iter();
+
+    while let Some(code) = codes.next() {
+        match code {
+            0 => style = Style::default(),
+            1 => style.is_bold = true,
+            2 => style.is_dimmed = true,
+            3 => style.is_italic = true,
+            4 => style.is_underline = true,
+            5 | 6 => style.is_blink = true,
+            7 => style.is_reverse = true,
+            8 => style.is_hidden = true,
+            9 => style.is_strikethrough = true,
+            21 | 22 => {
+                style.is_bold = false;
+                style.is_dimmed = false;
+            }
+            23 => style.is_italic = false,
+            24 => style.is_underline = false,
+            25 => style.is_blink = false,
+            27 => style.is_reverse = false,
+            28 => style.is_hidden = false,
+            29 => style.is_strikethrough = false,
+            30..=37 => style.foreground = Some(color(code - 30)),
+            38 => style.foreground = extended(&mut codes),
+            39 => style.foreground = None,
+            40..=47 => style.background = Some(color(code - 40)),
+            48 => style.background = extended(&mut codes),
+            49 => style.background = None,
+            90..=97 => style.foreground = Some(Color::Fixed(code - 90 + 8)),
+            100..=107 => style.background = Some(Color::Fixed(code - 100 + 8)),
+            _ => (),
+        }
+    }
+
+    style
+}
+
+fn color(code: u8) -> Color {
+    match code {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Purple,
+        6 => Color::Cyan,
+        _ => Color::White,
+    }
+}
+
+// 256 colors (`5;n`) or true colors (`2;r;g;b`)
+fn extended<I>(codes: &mut I) -> Option<Color>
+where
+    I: Iterator<Item = u8>,
+{
+    match codes.next()? {
+        5 => Some(Color::Fixed(codes.next()?)),
+        2 => Some(Color::RGB(codes.next()?, codes.next()?, codes.next()?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let (plain, styles) = parse("\x1b[33mcommit\x1b[m abc \x1b[1;38;5;10mHEAD\x1b[0m");
+
+        assert_eq!(plain, "commit abc HEAD");
+        assert_eq!(
+            styles,
+            vec![
+                (0, Color::Yellow.normal()),
+                (6, Style::default()),
+                (11, Color::Fixed(10).bold()),
+                (15, Style::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_other_sequences_test() {
+        let cases = vec![
+            ("foo\x1b[K", "foo"),
+            ("\x1b]8;;http://x\x07link\x1b]8;;\x1b\\", "link"),
+            ("a\x1b(Bb", "ab"),
+            ("trailing\x1b", "trailing"),
+            ("plain", "plain"),
+        ];
+
+        for (string, expected) in cases {
+            let (plain, styles) = parse(string);
+
+            assert_eq!(plain, expected, "String {:?}", string);
+            assert!(styles.is_empty(), "String {:?}", string);
+        }
+    }
+
+    #[test]
+    fn apply_test() {
+        let style = apply(Style::default(), "1;4;31;42");
+        assert_eq!(style, Color::Red.on(Color::Green).bold().underline());
+
+        assert_eq!(apply(style, "22;24;39;49"), Style::default());
+        assert_eq!(apply(style, ""), Style::default());
+        assert_eq!(apply(style, "0"), Style::default());
+        assert_eq!(
+            apply(Style::default(), "38;2;1;2;3;91"),
+            Color::Fixed(9).normal()
+        );
+        assert_eq!(
+            apply(Style::default(), "48;2;1;2;3"),
+            Style::default().on(Color::RGB(1, 2, 3))
+        );
+    }
+}
@@ -1,6 +1,12 @@
 //! Set of common types used through the app
 
+use crate::ansi;
+use crate::fields::Fields;
+use crate::fuzzy::predicates::is_word_separator;
+use ansi_term::Style;
 use async_std::sync::Arc;
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::fmt;
 use std::slice::Iter;
 use std::time::Instant;
@@ -8,20 +14,29 @@ use unicode_segmentation::UnicodeSegmentation;
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
+/// Max number of changes to the query that can be reverted
+const UNDO_LIMIT: usize = 100;
+
 /// The Prompt represents the current query, the cursor position in that query and when it was
 /// updated.
 ///
 /// When the query in the prompt changes the timestamp is updated to reflect that is a fresh query.
 /// This is then used to print to the UI only latest changes.
+///
+/// The prompt supports readline-style editing: text removed with the kill methods can be inserted
+/// back with `yank` and the latest changes to the query can be reverted with `undo`.
 #[derive(Debug, Clone)]
 pub struct Prompt {
     query: Vec<char>,
     cursor: usize,
     timestamp: Instant,
+    killed: Vec<char>,
+    undos: VecDeque<(Vec<char>, usize)>,
 }
 
 impl Prompt {
     pub fn add(&mut self, ch: char) {
+        self.save();
         self.query.insert(self.cursor, ch);
         self.cursor += 1;
         self.refresh();
@@ -29,6 +44,7 @@ impl Prompt {
 
     pub fn backspace(&mut self) -> bool {
         if self.cursor > 0 {
+            self.save();
             self.cursor -= 1;
             self.query.remove(self.cursor);
             self.refresh();
@@ -39,12 +55,95 @@ impl Prompt {
         false
     }
 
+    /// Remove the character under the cursor
+    pub fn delete(&mut self) -> bool {
+        if self.cursor < self.len() {
+            self.save();
+            self.query.remove(self.cursor);
+            self.refresh();
+
+            return true;
+        }
+
+        false
+    }
+
     pub fn clear(&mut self) {
-        self.query.clear();
+        if !self.is_empty() {
+            self.save();
+            self.killed = self.query.drain(..).collect();
+        }
         self.cursor = 0;
         self.refresh();
     }
 
+    /// Remove the word behind the cursor, keeping it to be yanked
+    pub fn kill_word_backward(&mut self) -> bool {
+        let start = self.word_start();
+
+        if start < self.cursor {
+            self.save();
+            self.killed = self.query.drain(start..self.cursor).collect();
+            self.cursor = start;
+            self.refresh();
+
+            return true;
+        }
+
+        false
+    }
+
+    /// Remove everything from the cursor to the end, keeping it to be yanked
+    pub fn kill_to_end(&mut self) -> bool {
+        if self.cursor < self.len() {
+            self.save();
+            self.killed = self.query.drain(self.cursor..).collect();
+            self.refresh();
+
+            return true;
+        }
+
+        false
+    }
+
+    /// Replace the whole query, moving the cursor to the end
+    pub fn set(&mut self, query: &str) {
+        self.save();
+        self.query = query.chars().collect();
+        self.cursor = self.len();
+        self.refresh();
+    }
+
+    /// Insert the last killed text at the cursor
+    pub fn yank(&mut self) -> bool {
+        if self.killed.is_empty() {
+            return false;
+        }
+
+        self.save();
+        let killed = self.killed.clone();
+        let len = killed.len();
+        self.query.splice(self.cursor..self.cursor, killed);
+        self.cursor += len;
+        self.refresh();
+
+        true
+    }
+
+    /// Revert the last change to the query
+    pub fn undo(&mut self) -> bool {
+        match self.undos.pop_back() {
+            Some((query, cursor)) => {
+                self.query = query;
+                self.cursor = cursor;
+                self.refresh();
+
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn left(&mut self) {
         if self.cursor > 0 {
             self.cursor -= 1;
@@ -57,6 +156,25 @@ impl Prompt {
         }
     }
 
+    /// Move the cursor to the start of the current (or previous) word
+    pub fn word_left(&mut self) {
+        self.cursor = self.word_start();
+    }
+
+    /// Move the cursor to the end of the current (or next) word
+    pub fn word_right(&mut self) {
+        let mut cursor = self.cursor;
+
+        while cursor < self.len() && is_separator(self.query[cursor]) {
+            cursor += 1;
+        }
+        while cursor < self.len() && !is_separator(self.query[cursor]) {
+            cursor += 1;
+        }
+
+        self.cursor = cursor;
+    }
+
     pub fn cursor_at_end(&mut self) {
         self.cursor = self.len();
     }
@@ -92,6 +210,34 @@ impl Prompt {
     pub fn refresh(&mut self) {
         self.timestamp = Instant::now();
     }
+
+    // Keep the current state of the query so it can be restored with undo
+    //
+    // The prompt is cloned for every change, so only the latest states are kept
+    fn save(&mut self) {
+        if self.undos.len() >= UNDO_LIMIT {
+            self.undos.pop_front();
+        }
+        self.undos.push_back((self.query.clone(), self.cursor));
+    }
+
+    // Position where the word behind the cursor starts, skipping separators
+    fn word_start(&self) -> usize {
+        let mut cursor = self.cursor.min(self.len());
+
+        while cursor > 0 && is_separator(self.query[cursor - 1]) {
+            cursor -= 1;
+        }
+        while cursor > 0 && !is_separator(self.query[cursor - 1]) {
+            cursor -= 1;
+        }
+
+        cursor
+    }
+}
+
+fn is_separator(ch: char) -> bool {
+    is_word_separator(ch.encode_utf8(&mut [0; 4]))
 }
 
 impl From<&String> for Prompt {
@@ -113,6 +259,8 @@ impl Default for Prompt {
             timestamp: Instant::now(),
             cursor: 0,
             query: vec![],
+            killed: vec![],
+            undos: VecDeque::new(),
         }
     }
 }
@@ -120,6 +268,23 @@ impl Default for Prompt {
 /// The Arc version of Letters
 pub type Text = Arc<Letters>;
 
+/// Options to read the input lines and build a Text out of them
+#[derive(Debug, Clone, Default)]
+pub struct TextOptions {
+    /// Fields of the displayed text used for matching
+    pub nth: Option<Fields>,
+    /// Fields of the line that are displayed (and matched)
+    pub with_nth: Option<Fields>,
+    /// Fields of the line that are printed when selected
+    pub accept_nth: Option<Fields>,
+    /// Remove the ANSI escape sequences, keeping their colors to display the line
+    pub ansi: bool,
+    /// Keep the empty lines of the input
+    pub keep_empty: bool,
+    /// Lines are separated by NUL bytes instead of newlines
+    pub read0: bool,
+}
+
 /// Text type builder
 #[derive(Debug, Clone)]
 pub struct TextBuilder;
@@ -130,12 +295,130 @@ impl TextBuilder {
 
         Arc::new(text)
     }
+
+    pub fn build_with_options(string: &str, options: &TextOptions) -> Text {
+        Self::build_line(string, 0, options)
+    }
+
+    /// Build the Text of the line in the given position of the input
+    pub fn build_line(string: &str, index: usize, options: &TextOptions) -> Text {
+        Arc::new(Self::letters(string, index, options))
+    }
+
+    /// Build the Text of a line of the input that might not be valid UTF-8
+    ///
+    /// Invalid sequences are replaced by `�` to display and match the line, but the
+    /// original bytes are printed when the whole line is selected.
+    pub fn build_raw_line(bytes: &[u8], index: usize, options: &TextOptions) -> Text {
+        match String::from_utf8_lossy(bytes) {
+            Cow::Borrowed(string) => Self::build_line(string, index, options),
+            Cow::Owned(string) => {
+                let mut text = Self::letters(&string, index, options);
+
+                // Otherwise the output is not the whole line
+                if options.accept_nth.is_none() && !options.ansi {
+                    text.bytes = Some(bytes.to_vec());
+                }
+
+                Arc::new(text)
+            }
+        }
+    }
+
+    fn letters(string: &str, index: usize, options: &TextOptions) -> Letters {
+        let (string, styles) = if options.ansi {
+            let (plain, changes) = ansi::parse(string);
+            let styles = grapheme_styles(&plain, &changes);
+
+            (Cow::Owned(plain), styles)
+        } else {
+            (Cow::Borrowed(string), vec![])
+        };
+        let string = string.as_ref();
+
+        let mut text: Letters = match &options.with_nth {
+            Some(fields) => {
+                let graphemes: Vec<String> = string.graphemes(true).map(String::from).collect();
+                let positions = fields.positions(&graphemes);
+                let selected: Vec<String> = positions
+                    .iter()
+                    .map(|&position| graphemes[position].clone())
+                    .collect();
+
+                let mut text = Letters::from_graphemes(selected.concat(), selected);
+                text.set_output(string.to_string());
+
+                if !styles.is_empty() {
+                    text.styles = positions.iter().map(|&position| styles[position]).collect();
+                }
+
+                text
+            }
+            None => {
+                let mut text: Letters = string.into();
+                text.styles = styles;
+
+                text
+            }
+        };
+
+        if let Some(fields) = &options.accept_nth {
+            text.set_output(fields.extract(string));
+        }
+
+        if let Some(fields) = &options.nth {
+            text.restrict(fields);
+        }
+
+        text.index = index;
+
+        text
+    }
+}
+
+/// Style of every grapheme of the string out of the positions (in bytes) where styles change
+///
+/// Strings without styles have no styles at all, to save some memory.
+fn grapheme_styles(string: &str, changes: &[(usize, Style)]) -> Vec<Style> {
+    if changes.iter().all(|(_, style)| *style == Style::default()) {
+        return vec![];
+    }
+
+    let mut style = Style::default();
+    let mut changes = changes.iter().peekable();
+
+    string
+        .grapheme_indices(true)
+        .map(|(position, _)| {
+            while let Some((_, next)) = changes.next_if(|(start, _)| *start <= position) {
+                style = *next;
+            }
+
+            style
+        })
+        .collect()
+}
+
+/// Part of a Letters that is used for matching
+///
+/// Each position maps a grapheme of the scope's text to its
+/// position in the original text.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub text: Text,
+    pub positions: Vec<usize>,
 }
 
 /// The collection of letters (Graphemes) of a string.
 ///
 /// These letters are the core part of the fuzzy matching algorithm.
 ///
+/// The string is what is displayed and matched, but the letters can have a
+/// different output, which is what gets printed when they are selected.
+///
+/// Letters built out of a line with ANSI colors also keep the style of every
+/// grapheme to display them.
+///
 /// This type is not used directly but through the Text type,
 /// which is an Arc wrapper around this type. We use Arc to reduce
 /// the String allocations between tasks as much as possible.
@@ -144,12 +427,21 @@ pub struct Letters {
     string: String,
     graphemes: Vec<String>,
     graphemes_lw: Vec<String>,
+    scope: Option<Scope>,
+    output: Option<String>,
+    styles: Vec<Style>,
+    bytes: Option<Vec<u8>>,
+    index: usize,
 }
 
 impl Letters {
     pub fn new(string: String) -> Self {
         let graphemes = string.graphemes(true).map(String::from).collect::<Vec<_>>();
 
+        Self::from_graphemes(string, graphemes)
+    }
+
+    fn from_graphemes(string: String, graphemes: Vec<String>) -> Self {
         let graphemes_lw = graphemes
             .iter()
             .map(|s| s.to_lowercase())
@@ -159,9 +451,69 @@ impl Letters {
             string,
             graphemes,
             graphemes_lw,
+            scope: None,
+            output: None,
+            styles: vec![],
+            bytes: None,
+            index: 0,
+        }
+    }
+
+    /// Position of the line in the input
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Set the string printed when these letters are selected
+    pub fn set_output(&mut self, output: String) {
+        self.output = Some(output);
+    }
+
+    /// The string printed when these letters are selected
+    pub fn output(&self) -> &str {
+        match &self.output {
+            Some(output) => output,
+            None => &self.string,
         }
     }
 
+    /// Style of the grapheme in the given position in the input line
+    pub fn style_at(&self, index: usize) -> Style {
+        self.styles.get(index).copied().unwrap_or_default()
+    }
+
+    /// The bytes printed when these letters are selected
+    ///
+    /// They are the same as the output unless the input line is not valid UTF-8
+    pub fn output_bytes(&self) -> &[u8] {
+        match &self.bytes {
+            Some(bytes) => bytes,
+            None => self.output().as_bytes(),
+        }
+    }
+
+    /// Only use the given fields for matching
+    pub fn restrict(&mut self, fields: &Fields) {
+        let positions: Vec<usize> = fields
+            .select(&self.graphemes)
+            .into_iter()
+            .flatten()
+            .collect();
+        let graphemes: Vec<String> = positions
+            .iter()
+            .map(|&position| self.graphemes[position].clone())
+            .collect();
+        let string = graphemes.concat();
+        let text = Arc::new(Self::from_graphemes(string, graphemes));
+
+        self.scope = Some(Scope { text, positions });
+    }
+
+    /// The part of the letters used for matching, if it is not the whole text
+    pub fn scope(&self) -> Option<&Scope> {
+        self.scope.as_ref()
+    }
+
     pub fn len(&self) -> usize {
         self.graphemes.len()
     }
@@ -214,3 +566,96 @@ impl fmt::Display for Letters {
         write!(f, "{}", self.string)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_words_test() {
+        let mut prompt = Prompt::from(&String::from("foo bar/baz"));
+
+        prompt.word_left();
+        assert_eq!(prompt.cursor_until_end(), 3);
+        prompt.word_left();
+        assert_eq!(prompt.cursor_until_end(), 7);
+        prompt.word_right();
+        assert_eq!(prompt.cursor_until_end(), 4);
+
+        prompt.cursor_at_end();
+        assert!(prompt.kill_word_backward());
+        assert_eq!(prompt.as_string(), "foo bar/");
+        assert!(prompt.kill_word_backward());
+        assert_eq!(prompt.as_string(), "foo ");
+        assert!(prompt.kill_word_backward());
+        assert!(prompt.is_empty());
+        assert!(!prompt.kill_word_backward());
+    }
+
+    #[test]
+    fn prompt_kill_and_yank_test() {
+        let mut prompt = Prompt::from(&String::from("foobar"));
+        let timestamp = prompt.timestamp();
+
+        prompt.left();
+        prompt.left();
+        prompt.left();
+        assert!(prompt.kill_to_end());
+        assert_eq!(prompt.as_string(), "foo");
+        assert!(prompt.timestamp() > timestamp);
+        assert!(!prompt.kill_to_end());
+
+        prompt.cursor_at_start();
+        assert!(prompt.yank());
+        assert_eq!(prompt.as_string(), "barfoo");
+        assert_eq!(prompt.cursor_until_end(), 3);
+
+        assert!(prompt.delete());
+        assert_eq!(prompt.as_string(), "baroo");
+        prompt.cursor_at_end();
+        assert!(!prompt.delete());
+    }
+
+    #[test]
+    fn prompt_undo_test() {
+        let mut prompt = Prompt::default();
+        assert!(!prompt.undo());
+
+        prompt.add('f');
+        prompt.add('o');
+        prompt.clear();
+        prompt.add('x');
+
+        let timestamp = prompt.timestamp();
+        assert!(prompt.undo());
+        assert!(prompt.is_empty());
+        assert!(prompt.timestamp() > timestamp);
+
+        assert!(prompt.undo());
+        assert_eq!(prompt.as_string(), "fo");
+        assert_eq!(prompt.cursor_until_end(), 0);
+
+        assert!(prompt.undo());
+        assert!(prompt.undo());
+        assert!(prompt.is_empty());
+        assert!(!prompt.undo());
+
+        // the cleared text can be yanked back
+        assert!(prompt.yank());
+        assert_eq!(prompt.as_string(), "fo");
+    }
+
+    #[test]
+    fn prompt_undo_limit_test() {
+        let mut prompt = Prompt::default();
+        for _ in 0..UNDO_LIMIT + 10 {
+            prompt.add('a');
+        }
+
+        for _ in 0..UNDO_LIMIT {
+            assert!(prompt.undo());
+        }
+        assert!(!prompt.undo());
+        assert_eq!(prompt.len(), 10);
+    }
+}
@@ -1,18 +1,24 @@
 //! Create and define the main configuration through toml files and command line args
 
 pub mod components;
+pub mod keys;
 pub mod styling;
 
 use components::*;
+use keys::KeysConfig;
 
-use crate::common::Result;
+use crate::common::{Result, TextOptions};
+use crate::fields::Fields;
+use crate::fuzzy::{Case, Tiebreak};
 use async_std::fs;
 use async_std::os::unix::io::AsRawFd;
 use async_std::sync::Arc;
 use clap::{value_t, ArgMatches};
 use serde::Deserialize;
+use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::num::NonZeroUsize;
 
 use crate::terminal_size::terminal_size;
 
@@ -26,6 +32,26 @@ pub struct Cfg {
     pub screen: ScreenConfig,
     #[serde(default)]
     pub initial_query: Option<String>,
+    #[serde(default)]
+    pub multi: bool,
+    #[serde(default)]
+    pub matcher: MatcherConfig,
+    #[serde(default)]
+    pub keys: KeysConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub frecency: FrecencyConfig,
+    #[serde(default)]
+    pub source: SourceConfig,
+    #[serde(default)]
+    pub walker: WalkerConfig,
+    #[serde(skip)]
+    pub input: TextOptions,
+    #[serde(skip)]
+    pub filter: Option<FilterConfig>,
+    #[serde(skip)]
+    pub print0: bool,
 
     #[serde(default)]
     pub prompt: PromptConfig,
@@ -35,18 +61,35 @@ pub struct Cfg {
     pub candidate: CandidateConfig,
     #[serde(default)]
     pub selection: SelectionConfig,
+    #[serde(default)]
+    pub marked: MarkedConfig,
+    #[serde(default)]
+    pub preview: PreviewConfig,
+}
+
+impl Cfg {
+    /// Bytes printed after every selected line
+    pub fn terminator(&self) -> &'static [u8] {
+        if self.print0 {
+            b"\0"
+        } else {
+            b"\n"
+        }
+    }
 }
 
 /// Configuration constructor
 #[derive(Debug, Default)]
 pub struct Configurator {
     config: Option<Cfg>,
+    error: Option<toml::de::Error>,
 }
 
 impl Configurator {
     pub fn new() -> Self {
         Self {
             config: Some(Cfg::default()),
+            error: None,
         }
     }
 
@@ -56,7 +99,7 @@ impl Configurator {
             let file_path = home.join(".config/scout.toml");
             let file_path = file_path.to_str();
             if let Some(path) = file_path {
-                match self.read_file(&path) {
+                match self.read_file(path) {
                     Ok(contents) => {
                         self.from_toml(&contents);
                     }
@@ -77,8 +120,17 @@ impl Configurator {
     }
 
     /// Parse toml configuration
+    ///
+    /// Invalid configuration is reported when the final Config is built
     pub fn from_toml<'a>(&'a mut self, contents: &str) -> &'a mut Self {
-        self.config = toml::from_str(contents).ok();
+        match toml::from_str(contents) {
+            Ok(config) => self.config = Some(config),
+            Err(err) => {
+                self.config = None;
+                self.error = Some(err);
+            }
+        };
+
         self
     }
 
@@ -95,6 +147,19 @@ impl Configurator {
         self
     }
 
+    /// Set configuration options from environment variables
+    pub fn from_env(&mut self) -> &mut Self {
+        if let Some(config) = &mut self.config {
+            if let Ok(command) = env::var("SCOUT_DEFAULT_COMMAND") {
+                if !command.is_empty() {
+                    config.source.set_command(&command);
+                }
+            }
+        }
+
+        self
+    }
+
     /// Set configuration options from command line args
     pub fn from_args<'a>(&'a mut self, args: &ArgMatches) -> &'a mut Self {
         if let Some(mut config) = self.config.take() {
@@ -108,6 +173,119 @@ impl Configurator {
                 config.screen.set_height(given);
             }
 
+            if let Ok(tabstop) = value_t!(args, "tabstop", NonZeroUsize) {
+                config.screen.set_tabstop(tabstop);
+            }
+
+            if let Ok(truncate) = value_t!(args, "truncate", Truncation) {
+                config.screen.set_truncate(truncate);
+            }
+
+            if args.is_present("multi") {
+                config.multi = true;
+            }
+
+            if let Some(values) = args.values_of("bind") {
+                for value in values {
+                    if let Ok(bindings) = keys::parse_bindings(value) {
+                        for (key, action) in bindings {
+                            config.keys.bind(key, action);
+                        }
+                    }
+                }
+            }
+
+            if let Some(file) = args.value_of("history") {
+                config.history.set_file(file);
+            }
+
+            if let Ok(size) = value_t!(args, "history-size", usize) {
+                config.history.set_size(size);
+            }
+
+            if let Some(key) = args.value_of("history-key") {
+                config.frecency.set_key(key);
+            }
+
+            if let Some(command) = args.value_of("preview") {
+                config.preview.set_command(command);
+            }
+
+            if let Ok(position) = value_t!(args, "preview-window", PreviewPosition) {
+                config.preview.set_position(position);
+            }
+
+            if args.is_present("path") {
+                config.matcher.set_path(true);
+            }
+
+            if let Ok(case) = value_t!(args, "case", Case) {
+                config.matcher.set_case(case);
+            }
+
+            if let Some(tiebreak) = args.value_of("tiebreak") {
+                if let Ok(tiebreak) = Tiebreak::parse_list(tiebreak) {
+                    config.matcher.set_tiebreak(tiebreak);
+                }
+            }
+
+            if args.is_present("no-sort") {
+                config.matcher.set_sort(false);
+            }
+
+            let delimiter = args.value_of("delimiter");
+            let fields = |name| {
+                args.value_of(name)
+                    .and_then(|value| Fields::parse(value, delimiter).ok())
+            };
+
+            config.input.nth = fields("nth");
+            config.input.with_nth = fields("with-nth");
+            config.input.accept_nth = fields("accept-nth");
+            config.input.ansi = args.is_present("ansi");
+            config.input.keep_empty = args.is_present("keep-empty");
+            config.input.read0 = args.is_present("read0");
+            config.print0 = args.is_present("print0");
+
+            if let Some(command) = args.value_of("command") {
+                config.source.set_command(command);
+                config.source.force();
+            }
+
+            if let Some(command) = args.value_of("live") {
+                config.source.set_command(command);
+                config.source.force();
+                config.source.set_live(true);
+            }
+
+            if let Ok(entry_type) = value_t!(args, "type", EntryType) {
+                config.walker.set_entry_type(entry_type);
+            }
+
+            if let Ok(max_depth) = value_t!(args, "max-depth", usize) {
+                config.walker.set_max_depth(max_depth);
+            }
+
+            if args.is_present("follow") {
+                config.walker.set_follow(true);
+            }
+
+            if let Some(globs) = args.values_of("exclude") {
+                config.walker.set_exclude(globs.map(String::from).collect());
+            }
+
+            if let Some(q) = args.value_of("filter") {
+                let mut filter = FilterConfig::new(q);
+
+                if let Ok(limit) = value_t!(args, "limit", usize) {
+                    filter.set_limit(limit);
+                }
+
+                filter.set_print_score(args.is_present("print-score"));
+
+                config.filter = Some(filter);
+            }
+
             if let Some(q) = args.value_of("search") {
                 let q = q.to_string();
                 config.initial_query = Some(q);
@@ -120,10 +298,14 @@ impl Configurator {
     }
 
     /// Generate the final Config instance
-    pub fn build(&mut self) -> Config {
+    pub fn build(&mut self) -> Result<Config> {
+        if let Some(err) = self.error.take() {
+            return Err(format!("Invalid configuration: {}", err).into());
+        }
+
         match self.config.take() {
-            Some(config) => Arc::new(config),
-            None => Default::default(),
+            Some(config) => Ok(Arc::new(config)),
+            None => Ok(Default::default()),
         }
     }
 
@@ -1,14 +1,23 @@
 //! Individual components configuration
 
 use super::styling::{Rule, Style};
+use crate::fuzzy::{Case, Options, Tiebreak};
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
 
 const MIN_HEIGHT: usize = 3;
 const MIN_WIDTH: usize = 4;
 
-#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
 enum Mode {
     #[serde(rename = "full")]
+    #[default]
     Full,
     #[serde(rename = "inline")]
     Inline,
@@ -16,16 +25,7 @@ enum Mode {
 
 impl Mode {
     pub fn is_full(&self) -> bool {
-        match self {
-            Mode::Full => true,
-            _ => false,
-        }
-    }
-}
-
-impl Default for Mode {
-    fn default() -> Self {
-        Mode::Full
+        matches!(self, Mode::Full)
     }
 }
 
@@ -38,6 +38,10 @@ pub struct ScreenConfig {
     width: Option<usize>,
     #[serde(default, alias = "lines")]
     height: Option<usize>,
+    #[serde(default)]
+    tabstop: Option<NonZeroUsize>,
+    #[serde(default)]
+    truncate: Option<Truncation>,
     #[serde(skip)]
     full_width: usize,
     #[serde(skip)]
@@ -89,12 +93,451 @@ impl ScreenConfig {
         MIN_HEIGHT.max(height)
     }
 
+    /// Number of columns between tab stops
+    pub fn tabstop(&self) -> usize {
+        self.tabstop.map_or(8, NonZeroUsize::get)
+    }
+
+    pub fn set_tabstop(&mut self, tabstop: NonZeroUsize) {
+        self.tabstop = Some(tabstop)
+    }
+
+    /// How lines longer than the screen are cut
+    pub fn truncate(&self) -> Truncation {
+        self.truncate.unwrap_or_default()
+    }
+
+    pub fn set_truncate(&mut self, truncate: Truncation) {
+        self.truncate = Some(truncate)
+    }
+
     pub fn set_full_size(&mut self, width: usize, height: usize) {
         self.full_width = width;
         self.full_height = height;
     }
 }
 
+/// How lines longer than the screen are cut
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum Truncation {
+    /// Hide the end of the line
+    #[serde(rename = "right")]
+    #[default]
+    Right,
+    /// Hide the start of the line
+    #[serde(rename = "left")]
+    Left,
+    /// Hide the middle of the line
+    #[serde(rename = "middle")]
+    Middle,
+}
+
+impl FromStr for Truncation {
+    type Err = ParseTruncationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "right" => Ok(Self::Right),
+            "left" => Ok(Self::Left),
+            "middle" => Ok(Self::Middle),
+            _ => Err(ParseTruncationError),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseTruncationError;
+
+impl fmt::Display for ParseTruncationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing truncation mode")
+    }
+}
+
+impl Error for ParseTruncationError {}
+
+/// Where the preview pane is placed
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum PreviewPosition {
+    /// Right half of the list
+    #[serde(rename = "right")]
+    #[default]
+    Right,
+    /// Bottom half of the list
+    #[serde(rename = "bottom")]
+    Bottom,
+}
+
+impl FromStr for PreviewPosition {
+    type Err = ParsePreviewPositionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "right" => Ok(Self::Right),
+            "bottom" => Ok(Self::Bottom),
+            _ => Err(ParsePreviewPositionError),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParsePreviewPositionError;
+
+impl fmt::Display for ParsePreviewPositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing preview position")
+    }
+}
+
+impl Error for ParsePreviewPositionError {}
+
+/// Preview pane configuration options
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PreviewConfig {
+    command: Option<String>,
+    position: Option<PreviewPosition>,
+    style_border: Option<Style>,
+}
+
+impl PreviewConfig {
+    /// Command to run for the selected candidate, `{}` is replaced by the candidate
+    pub fn command(&self) -> Option<String> {
+        self.command.clone()
+    }
+
+    pub fn set_command(&mut self, command: &str) {
+        self.command = Some(command.to_string())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.command.is_some()
+    }
+
+    /// Where the preview pane is placed
+    pub fn position(&self) -> PreviewPosition {
+        self.position.unwrap_or_default()
+    }
+
+    pub fn set_position(&mut self, position: PreviewPosition) {
+        self.position = Some(position)
+    }
+
+    /// Style for the line separating the preview from the list
+    pub fn style_border(&self) -> Style {
+        match &self.style_border {
+            Some(st) => st.clone(),
+            None => Style::new(vec![Rule::Dimmed]),
+        }
+    }
+}
+
+/// Command that produces the input lines
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct SourceConfig {
+    #[serde(default)]
+    command: Option<String>,
+    #[serde(skip)]
+    forced: bool,
+    #[serde(skip)]
+    live: bool,
+}
+
+impl SourceConfig {
+    /// Command to run for the input lines when nothing is piped to scout
+    pub fn command(&self) -> Option<String> {
+        self.command.clone()
+    }
+
+    pub fn set_command(&mut self, command: &str) {
+        self.command = Some(command.to_string())
+    }
+
+    /// Run the command even if something is piped to scout
+    pub fn force(&mut self) {
+        self.forced = true;
+    }
+
+    pub fn is_forced(&self) -> bool {
+        self.forced
+    }
+
+    /// Run the command again every time the query changes, instead of matching its lines
+    pub fn set_live(&mut self, live: bool) {
+        self.live = live;
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.live
+    }
+}
+
+/// Directory walker configuration options, used when there is no input nor command
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct WalkerConfig {
+    #[serde(default, rename = "type")]
+    entry_type: Option<EntryType>,
+    max_depth: Option<usize>,
+    follow: Option<bool>,
+    exclude: Option<Vec<String>>,
+}
+
+impl WalkerConfig {
+    /// Only list files or directories
+    pub fn entry_type(&self) -> Option<EntryType> {
+        self.entry_type
+    }
+
+    pub fn set_entry_type(&mut self, entry_type: EntryType) {
+        self.entry_type = Some(entry_type)
+    }
+
+    /// How deep to go in the directories, `1` only lists the entries of the current one
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = Some(max_depth)
+    }
+
+    /// Follow symbolic links
+    pub fn follow(&self) -> bool {
+        self.follow.unwrap_or(false)
+    }
+
+    pub fn set_follow(&mut self, follow: bool) {
+        self.follow = Some(follow)
+    }
+
+    /// Glob patterns of the paths to skip
+    pub fn exclude(&self) -> Vec<String> {
+        self.exclude.clone().unwrap_or_default()
+    }
+
+    pub fn set_exclude(&mut self, exclude: Vec<String>) {
+        self.exclude = Some(exclude)
+    }
+}
+
+/// Kind of the paths listed by the walker
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum EntryType {
+    #[serde(rename = "f", alias = "file")]
+    File,
+    #[serde(rename = "d", alias = "directory")]
+    Directory,
+}
+
+impl FromStr for EntryType {
+    type Err = ParseEntryTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f" | "file" => Ok(Self::File),
+            "d" | "directory" => Ok(Self::Directory),
+            _ => Err(ParseEntryTypeError),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseEntryTypeError;
+
+impl fmt::Display for ParseEntryTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing entry type")
+    }
+}
+
+impl Error for ParseEntryTypeError {}
+
+/// Fuzzy matcher configuration options
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct MatcherConfig {
+    path: Option<bool>,
+    case: Option<Case>,
+    tiebreak: Option<Vec<Tiebreak>>,
+    sort: Option<bool>,
+    #[serde(skip)]
+    boosts: Option<Arc<HashMap<String, f32>>>,
+}
+
+impl MatcherConfig {
+    /// Score candidates as file paths
+    pub fn path(&self) -> bool {
+        self.path.unwrap_or(false)
+    }
+
+    pub fn set_path(&mut self, path: bool) {
+        self.path = Some(path)
+    }
+
+    /// Case sensitivity mode
+    pub fn case(&self) -> Case {
+        self.case.unwrap_or_default()
+    }
+
+    pub fn set_case(&mut self, case: Case) {
+        self.case = Some(case)
+    }
+
+    /// Criteria to sort candidates with the same score
+    pub fn tiebreak(&self) -> Vec<Tiebreak> {
+        self.tiebreak.clone().unwrap_or_default()
+    }
+
+    pub fn set_tiebreak(&mut self, tiebreak: Vec<Tiebreak>) {
+        self.tiebreak = Some(tiebreak)
+    }
+
+    /// Sort candidates by score, otherwise keep the input order
+    pub fn sort(&self) -> bool {
+        self.sort.unwrap_or(true)
+    }
+
+    pub fn set_sort(&mut self, sort: bool) {
+        self.sort = Some(sort)
+    }
+
+    /// Extra weight of some candidates, based on the past selections
+    pub fn set_boosts(&mut self, boosts: HashMap<String, f32>) {
+        self.boosts = Some(Arc::new(boosts))
+    }
+
+    /// Options for the fuzzy search
+    pub fn options(&self) -> Options {
+        Options {
+            path: self.path(),
+            case: self.case(),
+            tiebreak: self.tiebreak(),
+            sort: self.sort(),
+            boosts: self.boosts.clone(),
+        }
+    }
+}
+
+/// Store of past selections configuration options
+///
+/// The store is only used with a key, so lines selected for different purposes don't mix.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct FrecencyConfig {
+    file: Option<String>,
+    #[serde(skip)]
+    key: Option<String>,
+}
+
+impl FrecencyConfig {
+    /// File where the selections are saved
+    ///
+    /// By default `$XDG_DATA_HOME/scout/selections.tsv` (or the equivalent of the platform)
+    pub fn file(&self) -> Option<PathBuf> {
+        match &self.file {
+            Some(file) => Some(PathBuf::from(file)),
+            None => dirs::data_dir().map(|dir| dir.join("scout").join("selections.tsv")),
+        }
+    }
+
+    pub fn set_file(&mut self, file: &str) {
+        self.file = Some(file.to_string())
+    }
+
+    /// Key of the selections of the current run
+    pub fn key(&self) -> Option<String> {
+        self.key.clone()
+    }
+
+    pub fn set_key(&mut self, key: &str) {
+        self.key = Some(key.to_string())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.key.is_some()
+    }
+}
+
+/// Non-interactive mode configuration options
+///
+/// In this mode the query is searched once and all the matches are printed
+#[derive(Debug, Clone, Default)]
+pub struct FilterConfig {
+    query: String,
+    limit: Option<usize>,
+    print_score: bool,
+}
+
+impl FilterConfig {
+    pub fn new(query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Query to search
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Max number of matches to print
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = Some(limit)
+    }
+
+    /// Print the score of each match before the line
+    pub fn print_score(&self) -> bool {
+        self.print_score
+    }
+
+    pub fn set_print_score(&mut self, print_score: bool) {
+        self.print_score = print_score
+    }
+}
+
+/// Query history configuration options
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct HistoryConfig {
+    file: Option<String>,
+    size: Option<usize>,
+    dedup: Option<bool>,
+}
+
+impl HistoryConfig {
+    /// File where the accepted queries are saved
+    pub fn file(&self) -> Option<String> {
+        self.file.clone()
+    }
+
+    pub fn set_file(&mut self, file: &str) {
+        self.file = Some(file.to_string())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Max number of queries kept in the file
+    pub fn size(&self) -> usize {
+        self.size.unwrap_or(1000)
+    }
+
+    pub fn set_size(&mut self, size: usize) {
+        self.size = Some(size)
+    }
+
+    /// Skip queries equal to the previous one
+    pub fn dedup(&self) -> bool {
+        self.dedup.unwrap_or(true)
+    }
+
+    pub fn set_dedup(&mut self, dedup: bool) {
+        self.dedup = Some(dedup)
+    }
+}
+
 /// Prompt UI component configuration options
 ///
 /// The prompt is where you write the search query
@@ -254,3 +697,62 @@ impl SelectionConfig {
         }
     }
 }
+
+/// UI options for the marked candidates in the list (multi-select mode)
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct MarkedConfig {
+    symbol: Option<String>,
+    style: Option<Style>,
+    style_symbol: Option<Style>,
+    style_match: Option<Style>,
+}
+
+impl MarkedConfig {
+    /// Symbol shown before the candidate's string
+    pub fn symbol(&self) -> String {
+        match &self.symbol {
+            Some(sym) => sym.clone(),
+            None => String::from("+ "),
+        }
+    }
+
+    /// Style for the whole string
+    pub fn style(&self) -> Style {
+        match &self.style {
+            Some(st) => st.clone(),
+            None => Default::default(),
+        }
+    }
+
+    /// Style for the symbol
+    pub fn style_symbol(&self) -> Style {
+        match &self.style_symbol {
+            Some(st) => st.clone(),
+            None => Style::new(vec![Rule::Bold]),
+        }
+    }
+
+    /// Style for the matches inside the candidate
+    pub fn style_match(&self) -> Style {
+        match &self.style_match {
+            Some(st) => st.clone(),
+            None => Style::new(vec![Rule::Underline, Rule::Bold]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_config_tabstop_test() {
+        let screen: ScreenConfig = toml::from_str("").unwrap();
+        assert_eq!(screen.tabstop(), 8);
+
+        let screen: ScreenConfig = toml::from_str("tabstop = 4").unwrap();
+        assert_eq!(screen.tabstop(), 4);
+
+        assert!(toml::from_str::<ScreenConfig>("tabstop = 0").is_err());
+    }
+}
@@ -0,0 +1,438 @@
+//! Key bindings definitions
+//!
+//! Every key pressed by the person is translated into an `Action` using a set of bindings. The
+//! default bindings can be changed in the config file with a `[keys]` section that maps key
+//! names to action names:
+//!
+//! ```text
+//! # toml file
+//! [keys]
+//! alt-j = "select-down"
+//! alt-k = "select-up"
+//! alt-enter = "toggle-mark"
+//! esc = "ignore"
+//! ```
+//!
+//! The same bindings can be given with the `--bind` option as a comma separated list of
+//! `key:action` pairs, like `--bind=alt-j:select-down,alt-k:select-up`.
+//!
+//! Some actions take an argument between parentheses, like `reload(git ls-files)`.
+
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use termion::event::Key;
+
+#[derive(Debug)]
+pub struct ParseKeyError(String);
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown key '{}'", self.0)
+    }
+}
+
+impl Error for ParseKeyError {}
+
+#[derive(Debug)]
+pub struct ParseActionError(String);
+
+impl fmt::Display for ParseActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown action '{}'", self.0)
+    }
+}
+
+impl Error for ParseActionError {}
+
+#[derive(Debug)]
+pub struct ParseBindingError(String);
+
+impl fmt::Display for ParseBindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing key binding '{}'", self.0)
+    }
+}
+
+impl Error for ParseBindingError {}
+
+/// Named actions that can be bound to keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Move the selection up
+    SelectUp,
+    /// Move the selection down
+    SelectDown,
+    /// Mark (or unmark) the selection (multi-select mode)
+    ToggleMark,
+    /// Mark (or unmark) the selection and move up (multi-select mode)
+    ToggleUp,
+    /// Mark (or unmark) the selection and move down (multi-select mode)
+    ToggleDown,
+    /// Show the part of a long selection that is hidden on the left
+    ScrollLeft,
+    /// Show the part of a long selection that is hidden on the right
+    ScrollRight,
+    /// Exit printing the selection
+    Accept,
+    /// Exit without selecting anything
+    Abort,
+    /// Remove the whole query
+    ClearQuery,
+    /// Remove the character behind the cursor
+    BackwardDeleteChar,
+    /// Remove the character under the cursor
+    DeleteChar,
+    /// Remove the word behind the cursor
+    BackwardKillWord,
+    /// Remove everything from the cursor to the end of the prompt
+    KillLine,
+    /// Insert the last removed text
+    Yank,
+    /// Revert the last change to the query
+    Undo,
+    /// Move the cursor one character to the left
+    BackwardChar,
+    /// Move the cursor one character to the right
+    ForwardChar,
+    /// Replace the query with the previous one in the history
+    PreviousHistory,
+    /// Replace the query with the next one in the history
+    NextHistory,
+    /// Move the cursor to the start of the previous word
+    BackwardWord,
+    /// Move the cursor to the end of the next word
+    ForwardWord,
+    /// Move the cursor to the beginning of the prompt
+    BeginningOfLine,
+    /// Move the cursor to the end of the prompt
+    EndOfLine,
+    /// Replace the input lines with the output of the command
+    ///
+    /// `{}` is replaced by the selection and `{q}` by the query
+    Reload(String),
+    /// Do nothing, useful to disable a default binding
+    Ignore,
+}
+
+impl FromStr for Action {
+    type Err = ParseActionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let command = s
+            .strip_prefix("reload(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .filter(|command| !command.trim().is_empty());
+
+        if let Some(command) = command {
+            return Ok(Self::Reload(command.to_string()));
+        }
+
+        let action = match s {
+            "select-up" => Self::SelectUp,
+            "select-down" => Self::SelectDown,
+            "toggle-mark" => Self::ToggleMark,
+            "toggle-up" => Self::ToggleUp,
+            "toggle-down" => Self::ToggleDown,
+            "scroll-left" => Self::ScrollLeft,
+            "scroll-right" => Self::ScrollRight,
+            "accept" => Self::Accept,
+            "abort" => Self::Abort,
+            "clear-query" => Self::ClearQuery,
+            "backward-delete-char" => Self::BackwardDeleteChar,
+            "delete-char" => Self::DeleteChar,
+            "backward-kill-word" => Self::BackwardKillWord,
+            "kill-line" => Self::KillLine,
+            "yank" => Self::Yank,
+            "undo" => Self::Undo,
+            "backward-char" => Self::BackwardChar,
+            "forward-char" => Self::ForwardChar,
+            "previous-history" => Self::PreviousHistory,
+            "next-history" => Self::NextHistory,
+            "backward-word" => Self::BackwardWord,
+            "forward-word" => Self::ForwardWord,
+            "beginning-of-line" => Self::BeginningOfLine,
+            "end-of-line" => Self::EndOfLine,
+            "ignore" => Self::Ignore,
+            _ => return Err(ParseActionError(s.to_string())),
+        };
+
+        Ok(action)
+    }
+}
+
+/// Parse a key name like `ctrl-j`, `alt-enter`, `pgdn`, `f2` or a single character
+pub fn parse_key(s: &str) -> Result<Key, ParseKeyError> {
+    let error = || ParseKeyError(s.to_string());
+
+    let key = match s {
+        "enter" | "return" => Key::Char('\n'),
+        "esc" => Key::Esc,
+        "tab" => Key::Char('\t'),
+        "btab" | "shift-tab" => Key::BackTab,
+        "space" => Key::Char(' '),
+        "bspace" | "backspace" => Key::Backspace,
+        "del" | "delete" => Key::Delete,
+        "insert" => Key::Insert,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pgup" | "page-up" => Key::PageUp,
+        "pgdn" | "page-down" => Key::PageDown,
+        // Terminals send the same bytes for these keys and for Enter and Tab
+        "ctrl-j" | "ctrl-m" => Key::Char('\n'),
+        "ctrl-i" => Key::Char('\t'),
+        "ctrl-space" => Key::Null,
+        "ctrl-_" | "ctrl-/" => Key::Ctrl('7'),
+        "alt-enter" => Key::Alt('\n'),
+        "alt-space" => Key::Alt(' '),
+        "alt-bspace" => Key::Alt('\x7f'),
+        _ => {
+            if let Some(ch) = single_char(s) {
+                Key::Char(ch)
+            } else if let Some(rest) = s.strip_prefix("ctrl-") {
+                match single_char(rest) {
+                    Some(ch) if ch.is_ascii_alphabetic() => Key::Ctrl(ch.to_ascii_lowercase()),
+                    _ => return Err(error()),
+                }
+            } else if let Some(rest) = s.strip_prefix("alt-") {
+                Key::Alt(single_char(rest).ok_or_else(error)?)
+            } else if let Some(rest) = s.strip_prefix('f') {
+                match rest.parse::<u8>() {
+                    Ok(n) if (1..=12).contains(&n) => Key::F(n),
+                    _ => return Err(error()),
+                }
+            } else {
+                return Err(error());
+            }
+        }
+    };
+
+    Ok(key)
+}
+
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Some(ch),
+        _ => None,
+    }
+}
+
+/// Parse a single `key:action` pair
+pub fn parse_binding(s: &str) -> Result<(Key, Action), Box<dyn Error + Send + Sync>> {
+    // The key itself can be a colon
+    let split = s
+        .get(1..)
+        .and_then(|rest| rest.find(':'))
+        .map(|i| i + 1)
+        .ok_or_else(|| ParseBindingError(s.to_string()))?;
+
+    let key = parse_key(&s[..split])?;
+    let action = s[split + 1..].parse()?;
+
+    Ok((key, action))
+}
+
+/// Parse a comma separated list of `key:action` pairs
+///
+/// Commas between parentheses are part of the action argument.
+pub fn parse_bindings(s: &str) -> Result<Vec<(Key, Action)>, Box<dyn Error + Send + Sync>> {
+    let mut bindings = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ',' if depth == 0 => {
+                bindings.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    bindings.push(&s[start..]);
+
+    bindings
+        .into_iter()
+        .filter(|binding| !binding.is_empty())
+        .map(parse_binding)
+        .collect()
+}
+
+/// Key bindings configuration
+///
+/// Only the custom bindings are kept, any other key falls back to the default bindings.
+#[derive(Debug, Clone, Default)]
+pub struct KeysConfig {
+    bindings: HashMap<Key, Action>,
+}
+
+impl KeysConfig {
+    /// Action bound to the given key
+    pub fn action(&self, key: &Key) -> Option<Action> {
+        self.bindings
+            .get(key)
+            .cloned()
+            .or_else(|| default_action(key))
+    }
+
+    pub fn bind(&mut self, key: Key, action: Action) {
+        self.bindings.insert(key, action);
+    }
+}
+
+fn default_action(key: &Key) -> Option<Action> {
+    let action = match key {
+        Key::Ctrl('p') | Key::Up => Action::SelectUp,
+        Key::Ctrl('n') | Key::Down => Action::SelectDown,
+        Key::Char('\t') => Action::ToggleDown,
+        Key::BackTab => Action::ToggleUp,
+        Key::Esc | Key::Alt('\u{0}') => Action::Abort,
+        Key::Char('\n') => Action::Accept,
+        Key::Ctrl('u') => Action::ClearQuery,
+        Key::Backspace => Action::BackwardDeleteChar,
+        Key::Delete | Key::Ctrl('d') => Action::DeleteChar,
+        Key::Ctrl('w') => Action::BackwardKillWord,
+        Key::Ctrl('k') => Action::KillLine,
+        Key::Ctrl('y') => Action::Yank,
+        Key::Ctrl('7') => Action::Undo,
+        Key::Left => Action::BackwardChar,
+        Key::Right => Action::ForwardChar,
+        Key::Ctrl('r') | Key::Alt('p') => Action::PreviousHistory,
+        Key::Alt('n') => Action::NextHistory,
+        Key::Alt('b') => Action::BackwardWord,
+        Key::Alt('f') => Action::ForwardWord,
+        Key::Ctrl('a') => Action::BeginningOfLine,
+        Key::Ctrl('e') => Action::EndOfLine,
+        Key::Alt('h') => Action::ScrollLeft,
+        Key::Alt('l') => Action::ScrollRight,
+        _ => return None,
+    };
+
+    Some(action)
+}
+
+struct KeysVisitor;
+
+impl<'de> Visitor<'de> for KeysVisitor {
+    type Value = KeysConfig;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a table of key names and actions")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut keys = KeysConfig::default();
+
+        while let Some((key, action)) = access.next_entry::<String, String>()? {
+            let parsed_key = parse_key(&key).map_err(de::Error::custom)?;
+            let parsed_action = action
+                .parse()
+                .map_err(|e| de::Error::custom(format!("{} for key '{}'", e, key)))?;
+
+            keys.bind(parsed_key, parsed_action);
+        }
+
+        Ok(keys)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeysConfig {
+    fn deserialize<D>(deserializer: D) -> Result<KeysConfig, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(KeysVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_key_test() {
+        let cases = vec![
+            ("ctrl-k", Key::Ctrl('k')),
+            ("ctrl-j", Key::Char('\n')),
+            ("alt-enter", Key::Alt('\n')),
+            ("alt-b", Key::Alt('b')),
+            ("pgdn", Key::PageDown),
+            ("f2", Key::F(2)),
+            ("enter", Key::Char('\n')),
+            ("btab", Key::BackTab),
+            ("x", Key::Char('x')),
+            ("f", Key::Char('f')),
+            (":", Key::Char(':')),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(parse_key(name).unwrap(), expected, "Key {:?}", name);
+        }
+
+        for name in &["ctrl-", "ctrl-1", "f13", "foo", ""] {
+            assert!(parse_key(name).is_err(), "Key {:?}", name);
+        }
+    }
+
+    #[test]
+    fn parse_bindings_test() {
+        let bindings = parse_bindings("ctrl-k:select-up,::accept,esc:ignore").unwrap();
+
+        assert_eq!(
+            bindings,
+            vec![
+                (Key::Ctrl('k'), Action::SelectUp),
+                (Key::Char(':'), Action::Accept),
+                (Key::Esc, Action::Ignore),
+            ]
+        );
+
+        let bindings = parse_bindings("ctrl-r:reload(ls -a, {q}),esc:abort").unwrap();
+
+        assert_eq!(
+            bindings,
+            vec![
+                (Key::Ctrl('r'), Action::Reload("ls -a, {q}".to_string())),
+                (Key::Esc, Action::Abort),
+            ]
+        );
+
+        assert!(parse_bindings("ctrl-r:reload()").is_err());
+        assert!(parse_bindings("ctrl-r:reload(ls").is_err());
+        assert!(parse_bindings("ctrl-j").is_err());
+        assert!(parse_bindings("ctrl-j:foo").is_err());
+        assert!(parse_bindings("foo:accept").is_err());
+    }
+
+    #[test]
+    fn keys_config_test() {
+        let keys: KeysConfig = toml::from_str("alt-j = \"select-down\"\nesc = \"ignore\"").unwrap();
+
+        assert_eq!(keys.action(&Key::Alt('j')), Some(Action::SelectDown));
+        assert_eq!(keys.action(&Key::Esc), Some(Action::Ignore));
+        assert_eq!(keys.action(&Key::Down), Some(Action::SelectDown));
+        assert_eq!(keys.action(&Key::Char('a')), None);
+
+        let error = toml::from_str::<KeysConfig>("ctrl-j = \"foo\"").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Unknown action 'foo' for key 'ctrl-j'"));
+
+        let error = toml::from_str::<KeysConfig>("hyper-j = \"accept\"").unwrap_err();
+        assert!(error.to_string().contains("Unknown key 'hyper-j'"));
+    }
+}
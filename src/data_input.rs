@@ -1,32 +1,128 @@
 //! Read lines from STDIN and signal when the STDIN has been consumed
+//!
+//! Lines are read as bytes, so lines that are not valid UTF-8 (like some file names) don't
+//! stop the program. Empty lines are skipped unless they are explicitly kept.
+//!
+//! Lines can also be separated by NUL bytes (`--read0`), to read file names with newlines
+//! or records of many lines.
 
-use crate::common::Result;
+use crate::common::{Result, TextOptions};
+use crate::config::Config;
 use crate::events::Event;
 use async_std::io;
 use async_std::prelude::*;
-use async_std::stream;
 use async_std::sync::Sender;
 
 /// Run the data input task
-pub async fn task<R>(stdin: R, sender: Sender<Event>) -> Result<()>
+pub async fn task<R>(config: Config, stdin: R, sender: Sender<Event>) -> Result<()>
 where
     R: io::Read + Unpin + Send + 'static,
 {
     log::trace!("starting to read input data");
 
-    let reader = io::BufReader::new(stdin);
-    let mut stream = reader
-        .lines()
-        .map(|res| res.expect("Error reading from STDIN"))
-        .filter(|line| !line.is_empty())
-        .map(Event::NewLine)
-        .chain(stream::once(Event::EOF));
+    let mut reader = io::BufReader::new(stdin);
 
-    while let Some(event) = stream.next().await {
-        sender.send(event).await;
+    loop {
+        match next_line(&mut reader, &config.input).await {
+            Ok(Some(line)) => sender.send(Event::NewLine(line)).await,
+            Ok(None) => break,
+            Err(e) => {
+                log::error!("failed to read from STDIN: {}", e);
+                break;
+            }
+        }
     }
 
+    sender.send(Event::EOF).await;
+
     log::trace!("input data done");
 
     Ok(())
 }
+
+/// Read the next line of the input, without the line terminator
+///
+/// It returns `None` when the whole input has been read.
+pub async fn next_line<R>(reader: &mut R, options: &TextOptions) -> io::Result<Option<Vec<u8>>>
+where
+    R: io::BufRead + Unpin,
+{
+    let delimiter = if options.read0 { b'\0' } else { b'\n' };
+
+    loop {
+        let mut line = vec![];
+
+        if reader.read_until(delimiter, &mut line).await? == 0 {
+            return Ok(None);
+        }
+
+        if line.last() == Some(&delimiter) {
+            line.pop();
+
+            if !options.read0 && line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+
+        if options.keep_empty || !line.is_empty() {
+            return Ok(Some(line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::task;
+
+    fn lines(input: &[u8], options: TextOptions) -> Vec<Vec<u8>> {
+        task::block_on(async {
+            let mut reader = io::BufReader::new(input);
+            let mut lines = vec![];
+
+            while let Some(line) = next_line(&mut reader, &options).await.unwrap() {
+                lines.push(line);
+            }
+
+            lines
+        })
+    }
+
+    #[test]
+    fn next_line_test() {
+        let input = b"foo\r\n\nb\xffr\nbaz";
+
+        assert_eq!(
+            lines(input, TextOptions::default()),
+            vec![b"foo".to_vec(), b"b\xffr".to_vec(), b"baz".to_vec()]
+        );
+
+        let options = TextOptions {
+            keep_empty: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            lines(input, options),
+            vec![
+                b"foo".to_vec(),
+                b"".to_vec(),
+                b"b\xffr".to_vec(),
+                b"baz".to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn next_line_read0_test() {
+        let input = b"foo\nbar\0\0baz\r\n\0";
+        let options = TextOptions {
+            read0: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            lines(input, options),
+            vec![b"foo\nbar".to_vec(), b"baz\r\n".to_vec()]
+        );
+    }
+}
@@ -2,33 +2,110 @@
 //!
 //! This task will collect all the input from STDIN and search over them on new queries.
 //! Once a search is done all the results will be sent to the screen.
+//!
+//! Searching is CPU bound work, so it doesn't happen in the async task itself. Lines and
+//! queries are sent to a dedicated worker thread that owns the pool of candidates. When a
+//! new query arrives the search of the previous one is cancelled, since its results would be
+//! discarded anyway, and queries that are still waiting in the queue are skipped.
+//!
+//! The results of previous queries are cached while the pool doesn't change, so typing
+//! only needs to search through the matches of the previous query.
 
-use crate::common::{Result, Text, TextBuilder};
+use crate::common::{Prompt, Result, Text, TextBuilder};
+use crate::config::Config;
 use crate::events::Event;
-use crate::fuzzy;
+use crate::fuzzy::{self, CancelToken};
 use async_std::prelude::*;
 use async_std::sync::{Receiver, Sender};
+use async_std::task;
 use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::thread;
 
 const BUFFER_LIMIT: usize = 5000;
 const POOL_LIMIT: usize = 50000;
 
+/// Work for the search worker
+#[derive(Debug)]
+enum Job {
+    /// Add a new line to the pool
+    Line(Vec<u8>),
+    /// Remove all the lines of the pool
+    Clear,
+    /// Search the pool again with the last query
+    Flush,
+    /// Search the pool with a new query
+    Search(Prompt, CancelToken),
+}
+
 /// Run the search engine task
-pub async fn task(mut input_recv: Receiver<Event>, output_sender: Sender<Event>) -> Result<()> {
+pub async fn task(
+    config: Config,
+    mut input_recv: Receiver<Event>,
+    output_sender: Sender<Event>,
+) -> Result<()> {
     log::trace!("starting search engine");
 
+    let (job_sender, job_recv) = mpsc::channel();
+    thread::Builder::new()
+        .name("search".into())
+        .spawn(move || worker(config, job_recv, output_sender))?;
+
+    let mut token = CancelToken::new();
+
+    while let Some(event) = input_recv.next().await {
+        let job = match event {
+            Event::NewLine(s) => Job::Line(s),
+            Event::ClearLines => Job::Clear,
+            Event::EOF => Job::Flush,
+            Event::Search(prompt) => {
+                // The results of the previous query are stale now
+                token.cancel();
+                token = CancelToken::new();
+
+                Job::Search(prompt, token.clone())
+            }
+            Event::Done | Event::Exit => break,
+            _ => continue,
+        };
+
+        if job_sender.send(job).is_err() {
+            break;
+        }
+    }
+
+    // Dropping the sender stops the worker
+    token.cancel();
+
+    log::trace!("search engine done");
+
+    Ok(())
+}
+
+/// Run the searches in the current thread
+fn worker(config: Config, jobs: mpsc::Receiver<Job>, output_sender: Sender<Event>) {
+    log::trace!("starting search worker");
+
+    let mut cache = fuzzy::Cache::new(config.matcher.options());
     let mut pool: VecDeque<Text> = VecDeque::new();
     let mut count = 0;
+    let mut index = 0;
     let mut query = String::from("");
+    let mut token = CancelToken::new();
+    let live = config.source.is_live();
 
-    while let Some(event) = input_recv.next().await {
-        match event {
-            Event::NewLine(s) => {
-                log::trace!("line: {:?}", s);
+    for job in jobs {
+        match job {
+            Job::Line(s) => {
+                log::trace!("line: {:?}", String::from_utf8_lossy(&s));
 
                 // Push the new line into the main pool
-                pool.push_back(TextBuilder::build(&s));
+                pool.push_back(TextBuilder::build_raw_line(&s, index, &config.input));
                 count += 1;
+                index += 1;
+
+                // Previous results don't include the new line
+                cache.clear();
 
                 // The pool might be full (too many lines in memory)
                 // so we drop the first line
@@ -44,34 +121,59 @@ pub async fn task(mut input_recv: Receiver<Event>, output_sender: Sender<Event>)
                 // to the screen
                 if count > BUFFER_LIMIT {
                     count = 0;
-                    let matches = fuzzy::search(&query, &pool);
-                    output_sender
-                        .send(Event::Flush((matches, pool.len())))
-                        .await;
+                    if let Some(matches) = cache.search(&query, &pool, &token) {
+                        let flush = Event::Flush((matches, pool.len()));
+                        task::block_on(output_sender.send(flush));
+                    }
                 }
             }
-            Event::EOF => {
+            Job::Clear => {
+                log::trace!("clearing the pool");
+
+                // Indexes keep growing, so marks of the previous lines don't mark new ones
+                pool.clear();
+                count = 0;
+                cache.clear();
+
+                let flush = Event::Flush((vec![], 0));
+                task::block_on(output_sender.send(flush));
+            }
+            Job::Flush => {
                 log::trace!("all input data done");
-                let matches = fuzzy::search(&query, &pool);
-                output_sender
-                    .send(Event::Flush((matches, pool.len())))
-                    .await;
+
+                // If the search is cancelled there is a new query in the queue
+                // that will refresh the results
+                if let Some(matches) = cache.search(&query, &pool, &token) {
+                    let flush = Event::Flush((matches, pool.len()));
+                    task::block_on(output_sender.send(flush));
+                }
             }
-            Event::Search(prompt) => {
-                query = prompt.as_string();
-                log::trace!("performing new search: '{}'", query);
+            Job::Search(prompt, search_token) => {
+                // The lines of a live command already match the query
+                query = if live {
+                    String::new()
+                } else {
+                    prompt.as_string()
+                };
+                token = search_token;
 
-                let matches = fuzzy::search(&query, &pool);
-                let results = Event::SearchDone((matches, pool.len(), prompt.timestamp()));
+                if token.is_cancelled() {
+                    log::trace!("skipping stale search: '{}'", query);
+                    continue;
+                }
 
-                output_sender.send(results).await;
+                log::trace!("performing new search: '{}'", query);
+
+                match cache.search(&query, &pool, &token) {
+                    Some(matches) => {
+                        let results = Event::SearchDone((matches, pool.len(), prompt.timestamp()));
+                        task::block_on(output_sender.send(results));
+                    }
+                    None => log::trace!("search cancelled: '{}'", query),
+                }
             }
-            Event::Done | Event::Exit => break,
-            _ => (),
         };
     }
 
-    log::trace!("search engine done");
-
-    Ok(())
+    log::trace!("search worker done");
 }
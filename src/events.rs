@@ -2,21 +2,37 @@
 //!
 //! All tasks communicate between them using events.
 
-use crate::common::Prompt;
+use crate::common::{Prompt, Text};
 use crate::fuzzy::Candidate;
 use std::time::Instant;
 
 #[derive(Clone, Debug)]
 pub enum Event {
-    /// New line from STDIN
-    NewLine(String),
+    /// New line from STDIN, which might not be valid UTF-8
+    NewLine(Vec<u8>),
     /// Signal that STDIN is done
     EOF,
+    /// The command that produces the input lines failed, with the reason
+    SourceError(String),
+    /// Replace the input lines with the output of the given command
+    Reload(String),
+    /// Forget the input lines, the lines of a reload are coming
+    ClearLines,
 
     /// Move selection up
     Up,
     /// Move selection down
     Down,
+    /// Toggle the mark of the selection (multi-select mode)
+    Toggle,
+    /// Toggle the mark of the selection and move down (multi-select mode)
+    ToggleDown,
+    /// Toggle the mark of the selection and move up (multi-select mode)
+    ToggleUp,
+    /// Scroll the selection to the left
+    ScrollLeft,
+    /// Scroll the selection to the right
+    ScrollRight,
     /// Exit the program without selecting anything
     Exit,
     /// Exit with selection
@@ -29,6 +45,14 @@ pub enum Event {
     /// Flush the screen with the given list of candidates
     Flush((Vec<Candidate>, usize)),
 
+    /// Run the preview command for the given candidate
+    Preview(Text),
+    /// Output of the preview command for the candidate with the given input index
+    PreviewDone((usize, Vec<String>)),
+
+    /// The terminal has a new size (columns, rows)
+    Resize((usize, usize)),
+
     /// NO-OP. Used to make some internal streams happy
     Ignore,
 }
@@ -0,0 +1,354 @@
+//! Split lines into fields
+//!
+//! Fields are used to restrict which parts of a line are taken into account, for example
+//! to only match the second column of a table. They are selected with a comma separated
+//! list of ranges (1-based, negative numbers count from the end):
+//!
+//! ```text
+//! 1      first field
+//! -1     last field
+//! 2..    from the second field until the end
+//! ..3    from the first field until the third one
+//! 2..-2  from the second field until the penultimate one
+//! ..     all fields
+//! ```
+//!
+//! By default fields are separated AWK-style (by whitespace), but a literal delimiter can be
+//! used as well. Each field includes the delimiter that follows it.
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use unicode_segmentation::UnicodeSegmentation;
+
+const RANGE_SEPARATOR: &str = "..";
+
+#[derive(Debug)]
+pub struct ParseFieldsError;
+
+impl fmt::Display for ParseFieldsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing fields")
+    }
+}
+
+impl Error for ParseFieldsError {}
+
+/// How to separate one field from the next one
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Delimiter {
+    /// Any sequence of spaces or tabs
+    #[default]
+    Whitespace,
+    /// The exact given sequence of graphemes
+    Literal(Vec<String>),
+}
+
+impl From<&str> for Delimiter {
+    fn from(string: &str) -> Self {
+        if string.is_empty() {
+            return Self::Whitespace;
+        }
+
+        let string = string.replace("\\t", "\t");
+        let graphemes = string.graphemes(true).map(String::from).collect();
+
+        Self::Literal(graphemes)
+    }
+}
+
+/// Inclusive range of fields
+///
+/// Indexes start at 1 and negative ones start from the end
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldRange {
+    start: Option<isize>,
+    end: Option<isize>,
+}
+
+impl FieldRange {
+    /// Get the 0-based inclusive indexes of the range for the given number of fields, if any
+    fn resolve(&self, len: usize) -> Option<(usize, usize)> {
+        let len = len as isize;
+        let resolve = |index: isize| if index < 0 { len + index } else { index - 1 };
+
+        let start = self.start.map_or(0, resolve).max(0);
+        let end = self.end.map_or(len - 1, resolve).min(len - 1);
+
+        if start > end {
+            None
+        } else {
+            Some((start as usize, end as usize))
+        }
+    }
+}
+
+impl FromStr for FieldRange {
+    type Err = ParseFieldsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let index = |s: &str| -> Result<Option<isize>, Self::Err> {
+            if s.is_empty() {
+                return Ok(None);
+            }
+
+            match s.parse::<isize>() {
+                Ok(0) | Err(_) => Err(ParseFieldsError),
+                Ok(n) => Ok(Some(n)),
+            }
+        };
+
+        let range = match s.find(RANGE_SEPARATOR) {
+            Some(position) => Self {
+                start: index(&s[..position])?,
+                end: index(&s[(position + RANGE_SEPARATOR.len())..])?,
+            },
+            None => {
+                let n = index(s)?.ok_or(ParseFieldsError)?;
+
+                Self {
+                    start: Some(n),
+                    end: Some(n),
+                }
+            }
+        };
+
+        Ok(range)
+    }
+}
+
+/// Selection of fields of a line
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Fields {
+    delimiter: Delimiter,
+    ranges: Vec<FieldRange>,
+}
+
+impl Fields {
+    pub fn new(ranges: Vec<FieldRange>, delimiter: Delimiter) -> Self {
+        Self { delimiter, ranges }
+    }
+
+    /// Parse a comma separated list of ranges
+    pub fn parse(ranges: &str, delimiter: Option<&str>) -> Result<Self, ParseFieldsError> {
+        let ranges = ranges
+            .split(',')
+            .map(|range| range.trim().parse())
+            .collect::<Result<Vec<FieldRange>, ParseFieldsError>>()?;
+        let delimiter = delimiter.map(Delimiter::from).unwrap_or_default();
+
+        Ok(Self::new(ranges, delimiter))
+    }
+
+    /// Get the positions of the selected fields inside the given graphemes
+    ///
+    /// The fields are returned in the same order as the ranges
+    pub fn select(&self, graphemes: &[String]) -> Vec<Range<usize>> {
+        let fields = self.split(graphemes);
+
+        self.ranges
+            .iter()
+            .filter_map(|range| range.resolve(fields.len()))
+            .flat_map(|(start, end)| fields[start..=end].iter().cloned())
+            .collect()
+    }
+
+    /// Get the selected fields of the given string
+    ///
+    /// The delimiter at the end of the last field is removed
+    pub fn extract(&self, string: &str) -> String {
+        let graphemes: Vec<String> = string.graphemes(true).map(String::from).collect();
+
+        self.positions(&graphemes)
+            .into_iter()
+            .map(|position| graphemes[position].as_str())
+            .collect()
+    }
+
+    /// Get the position of every grapheme of the selected fields
+    ///
+    /// The delimiter at the end of the last field is left out, like in `extract`
+    pub fn positions(&self, graphemes: &[String]) -> Vec<usize> {
+        let mut positions: Vec<usize> = self.select(graphemes).into_iter().flatten().collect();
+
+        match &self.delimiter {
+            Delimiter::Whitespace => {
+                while let Some(&last) = positions.last() {
+                    if graphemes[last] != " " && graphemes[last] != "\t" {
+                        break;
+                    }
+                    positions.pop();
+                }
+            }
+            Delimiter::Literal(delimiter) => {
+                let selected: Vec<&str> = positions
+                    .iter()
+                    .map(|&position| graphemes[position].as_str())
+                    .collect();
+
+                if selected.ends_with(&delimiter.iter().map(String::as_str).collect::<Vec<_>>()) {
+                    positions.truncate(positions.len() - delimiter.len());
+                }
+            }
+        };
+
+        positions
+    }
+
+    /// Get the positions of every field inside the given graphemes
+    pub fn split(&self, graphemes: &[String]) -> Vec<Range<usize>> {
+        let mut fields = vec![];
+        let mut start = 0;
+        let mut index = 0;
+
+        match &self.delimiter {
+            Delimiter::Whitespace => {
+                let is_whitespace = |g: &str| g == " " || g == "\t";
+                let mut in_field = false;
+
+                while index < graphemes.len() {
+                    if !is_whitespace(&graphemes[index]) {
+                        in_field = true;
+                        index += 1;
+                        continue;
+                    }
+
+                    while index < graphemes.len() && is_whitespace(&graphemes[index]) {
+                        index += 1;
+                    }
+
+                    if in_field {
+                        fields.push(start..index);
+                        start = index;
+                        in_field = false;
+                    }
+                }
+            }
+            Delimiter::Literal(delimiter) => {
+                while index + delimiter.len() <= graphemes.len() {
+                    if graphemes[index..(index + delimiter.len())] == delimiter[..] {
+                        index += delimiter.len();
+                        fields.push(start..index);
+                        start = index;
+                    } else {
+                        index += 1;
+                    }
+                }
+            }
+        };
+
+        if start < graphemes.len() {
+            fields.push(start..graphemes.len());
+        }
+
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graphemes(string: &str) -> Vec<String> {
+        string.graphemes(true).map(String::from).collect()
+    }
+
+    fn strings(string: &str, ranges: Vec<Range<usize>>) -> Vec<String> {
+        let graphemes = graphemes(string);
+
+        ranges
+            .into_iter()
+            .map(|range| graphemes[range].concat())
+            .collect()
+    }
+
+    #[test]
+    fn field_range_from_str_test() {
+        let cases = vec![
+            ("1", Some((Some(1), Some(1)))),
+            ("-1", Some((Some(-1), Some(-1)))),
+            ("2..", Some((Some(2), None))),
+            ("..3", Some((None, Some(3)))),
+            ("2..-2", Some((Some(2), Some(-2)))),
+            ("..", Some((None, None))),
+            ("0", None),
+            ("", None),
+            ("a", None),
+            ("1..b", None),
+        ];
+
+        for (string, expected) in cases {
+            let expected = expected.map(|(start, end)| FieldRange { start, end });
+            let actual = string.parse::<FieldRange>().ok();
+
+            assert_eq!(actual, expected, "Range {:?}", string);
+        }
+    }
+
+    #[test]
+    fn fields_split_test() {
+        let cases = vec![
+            ("foo bar  baz", None, vec!["foo ", "bar  ", "baz"]),
+            ("  foo\tbar ", None, vec!["  foo\t", "bar "]),
+            ("foo,bar,,baz", Some(","), vec!["foo,", "bar,", ",", "baz"]),
+            ("foo::bar::", Some("::"), vec!["foo::", "bar::"]),
+            ("foo\tbar", Some("\\t"), vec!["foo\t", "bar"]),
+            ("y̆公 🍣", None, vec!["y̆公 ", "🍣"]),
+            ("", None, vec![]),
+        ];
+
+        for (string, delimiter, expected) in cases {
+            let fields = Fields::parse("1", delimiter).unwrap();
+            let actual = strings(string, fields.split(&graphemes(string)));
+
+            assert_eq!(actual, expected, "Fields of {:?}", string);
+        }
+    }
+
+    #[test]
+    fn fields_extract_test() {
+        let cases = vec![
+            ("1", None, "foo bar baz", "foo"),
+            ("2..", None, "foo  bar baz ", "bar baz"),
+            ("-1,1", None, "foo bar baz", "bazfoo"),
+            ("1", Some(","), "foo,bar", "foo"),
+            ("..2", Some("::"), "foo::bar::baz", "foo::bar"),
+            ("3", None, "foo bar", ""),
+        ];
+
+        for (ranges, delimiter, string, expected) in cases {
+            let fields = Fields::parse(ranges, delimiter).unwrap();
+
+            assert_eq!(
+                fields.extract(string),
+                expected,
+                "Fields {:?} of {:?}",
+                ranges,
+                string
+            );
+        }
+    }
+
+    #[test]
+    fn fields_select_test() {
+        let cases = vec![
+            ("1", "foo bar baz", vec!["foo "]),
+            ("-1", "foo bar baz", vec!["baz"]),
+            ("2..", "foo bar baz", vec!["bar ", "baz"]),
+            ("..2", "foo bar baz", vec!["foo ", "bar "]),
+            ("3,1", "foo bar baz", vec!["baz", "foo "]),
+            ("2..-2", "a b c d", vec!["b ", "c "]),
+            ("..", "a b", vec!["a ", "b"]),
+            ("4", "foo bar baz", vec![]),
+            ("-4", "foo bar baz", vec![]),
+        ];
+
+        for (ranges, string, expected) in cases {
+            let fields = Fields::parse(ranges, None).unwrap();
+            let actual = strings(string, fields.select(&graphemes(string)));
+
+            assert_eq!(actual, expected, "Fields {:?} of {:?}", ranges, string);
+        }
+    }
+}
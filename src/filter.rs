@@ -0,0 +1,220 @@
+//! Non-interactive mode
+//!
+//! Instead of running the whole interface, all the input lines are searched once with the given
+//! query and the matches are printed to STDOUT, sorted the same way as in the interactive mode.
+//! No PTTY is needed, so it can be used in scripts.
+//!
+//! The lines come from the same source as in the interactive mode: STDIN, the source command or
+//! the paths of the current directory (see `source`).
+
+use crate::common::{Result, Text, TextBuilder};
+use crate::config::Config;
+use crate::events::Event;
+use crate::fuzzy;
+use crate::source;
+use async_std::io;
+use async_std::prelude::*;
+use async_std::sync;
+
+const CHANNEL_SIZE: usize = 1024;
+
+/// Search the input lines and print the matches to the output
+///
+/// STDIN is `None` when nothing is piped to scout. It returns the number of printed matches.
+pub async fn run<R, W>(config: Config, stdin: Option<R>, mut stdout: W) -> Result<usize>
+where
+    R: io::Read + Unpin + Send + 'static,
+    W: io::Write + Unpin,
+{
+    let filter = config.filter.clone().unwrap_or_default();
+
+    log::trace!("filtering input with '{}'", filter.query());
+
+    let pool = read(&config, stdin, filter.query()).await?;
+    let matches = fuzzy::search_with_options(filter.query(), &pool, config.matcher.options());
+    let limit = filter.limit().unwrap_or(matches.len());
+
+    let mut count = 0;
+    for candidate in matches.iter().take(limit) {
+        if filter.print_score() {
+            stdout
+                .write_all(format!("{}\t", candidate.score()).as_bytes())
+                .await?;
+        }
+
+        stdout.write_all(candidate.output_bytes()).await?;
+        stdout.write_all(config.terminator()).await?;
+        count += 1;
+    }
+
+    stdout.flush().await?;
+
+    log::trace!("printed {} matches", count);
+
+    Ok(count)
+}
+
+/// Read all the input lines
+///
+/// It fails if the source command fails, instead of searching only some of its lines.
+async fn read<R>(config: &Config, stdin: Option<R>, query: &str) -> Result<Vec<Text>>
+where
+    R: io::Read + Unpin + Send + 'static,
+{
+    let (input_sender, mut input_recv) = sync::channel(CHANNEL_SIZE);
+    let (error_sender, mut error_recv) = sync::channel(1);
+
+    let (reading, _) = source::open(config, stdin, query, &input_sender, &error_sender);
+    drop(input_sender);
+    drop(error_sender);
+
+    let mut pool: Vec<Text> = vec![];
+
+    while let Some(event) = input_recv.next().await {
+        match event {
+            Event::NewLine(line) => pool.push(TextBuilder::build_raw_line(
+                &line,
+                pool.len(),
+                &config.input,
+            )),
+            Event::EOF => break,
+            _ => (),
+        };
+    }
+
+    reading.await?;
+
+    match error_recv.next().await {
+        Some(Event::SourceError(error)) => Err(error.into()),
+        _ => Ok(pool),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::components::FilterConfig;
+    use crate::config::Cfg;
+    use async_std::sync::Arc;
+    use async_std::task;
+
+    fn filter(input: &'static str, filter: FilterConfig) -> (usize, String) {
+        let config = Arc::new(Cfg {
+            filter: Some(filter),
+            ..Default::default()
+        });
+        let mut output = vec![];
+
+        let count = task::block_on(run(config, Some(input.as_bytes()), &mut output)).unwrap();
+
+        (count, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn filter_run_test() {
+        let input = "src/lib.rs\nsrc/main.rs\n\nREADME.md\nmain.rs\n";
+
+        let (count, output) = filter(input, FilterConfig::new("main"));
+        assert_eq!(count, 2);
+        assert_eq!(output, "main.rs\nsrc/main.rs\n");
+
+        let (count, output) = filter(input, FilterConfig::new(""));
+        assert_eq!(count, 4);
+        assert_eq!(output, "src/lib.rs\nsrc/main.rs\nREADME.md\nmain.rs\n");
+
+        let (count, output) = filter(input, FilterConfig::new("zzz"));
+        assert_eq!(count, 0);
+        assert_eq!(output, "");
+    }
+
+    #[test]
+    fn filter_run_with_limit_and_score_test() {
+        let input = "src/lib.rs\nsrc/main.rs\nmain.rs\n";
+        let mut config = FilterConfig::new("main");
+        config.set_limit(1);
+        config.set_print_score(true);
+
+        let (count, output) = filter(input, config);
+        assert_eq!(count, 1);
+
+        let (score, line) = output.trim_end().split_once('\t').unwrap();
+        assert!(score.parse::<f32>().unwrap() > 0.0);
+        assert_eq!(line, "main.rs");
+    }
+
+    #[test]
+    fn filter_run_raw_lines_test() {
+        let input: &[u8] = b"caf\xe9.txt\n\nnotes.txt\n";
+        let mut cfg = Cfg {
+            filter: Some(FilterConfig::new("caf")),
+            ..Default::default()
+        };
+
+        // invalid UTF-8 is matched but printed as it was
+        let mut output = vec![];
+        let count = task::block_on(run(Arc::new(cfg.clone()), Some(input), &mut output)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(output, b"caf\xe9.txt\n");
+
+        cfg.filter = Some(FilterConfig::new(""));
+        cfg.input.keep_empty = true;
+
+        let mut output = vec![];
+        let count = task::block_on(run(Arc::new(cfg), Some(input), &mut output)).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(output, b"caf\xe9.txt\n\nnotes.txt\n");
+    }
+
+    #[test]
+    fn filter_run_null_delimited_test() {
+        let input: &[u8] = b"two\nlines\0main.rs\0";
+        let mut cfg = Cfg {
+            filter: Some(FilterConfig::new("lines")),
+            print0: true,
+            ..Default::default()
+        };
+        cfg.input.read0 = true;
+
+        let mut output = vec![];
+        let count = task::block_on(run(Arc::new(cfg), Some(input), &mut output)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(output, b"two\nlines\0");
+    }
+
+    #[test]
+    fn filter_run_source_command_test() {
+        let mut cfg = Cfg {
+            filter: Some(FilterConfig::new("main")),
+            ..Default::default()
+        };
+        cfg.source
+            .set_command("printf 'src/lib.rs\\nsrc/main.rs\\n'");
+
+        // the command runs when nothing is piped
+        let mut output = vec![];
+        let count = task::block_on(run(Arc::new(cfg.clone()), None::<&[u8]>, &mut output)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(output, b"src/main.rs\n");
+
+        // but the piped lines go first, unless the command is forced
+        let input: &[u8] = b"main.rs\n";
+        let mut output = vec![];
+        let count = task::block_on(run(Arc::new(cfg.clone()), Some(input), &mut output)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(output, b"main.rs\n");
+
+        cfg.source.force();
+        let mut output = vec![];
+        let count = task::block_on(run(Arc::new(cfg.clone()), Some(input), &mut output)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(output, b"src/main.rs\n");
+
+        cfg.source
+            .set_command("echo main.rs; echo 'no such file' >&2; exit 2");
+        let result = task::block_on(run(Arc::new(cfg), None::<&[u8]>, vec![]));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "'echo main.rs; echo 'no such file' >&2; exit 2' exited with status 2: no such file"
+        );
+    }
+}
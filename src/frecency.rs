@@ -0,0 +1,315 @@
+//! Store of past selections
+//!
+//! Every selected line is saved with how many times and when it was last selected, under the
+//! key given with `--history-key`. The frecency of a line (frequency and recency) is then used
+//! to rank the lines selected more often and more recently higher in later runs with the same
+//! key.
+//!
+//! The store is a tab separated file with one line per selection:
+//!
+//! ```text
+//! <key>\t<count>\t<last selection, seconds since epoch>\t<line>
+//! ```
+
+use crate::common::{Result, Text};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+// Selections older than this are removed when pruning
+const PRUNE_AGE: u64 = 90 * DAY;
+
+/// A line selected in the past
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    key: String,
+    count: u32,
+    last: u64,
+    line: String,
+}
+
+impl Entry {
+    /// Selected line
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Number of times the line was selected
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Frequency of the selection weighted by how recent it is
+    pub fn frecency(&self, now: u64) -> f32 {
+        let age = now.saturating_sub(self.last);
+        let weight = if age < HOUR {
+            4.0
+        } else if age < DAY {
+            2.0
+        } else if age < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+
+        self.count as f32 * weight
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(4, '\t');
+
+        Some(Self {
+            key: parts.next()?.to_string(),
+            count: parts.next()?.parse().ok()?,
+            last: parts.next()?.parse().ok()?,
+            line: parts.next()?.to_string(),
+        })
+    }
+}
+
+/// Selections of all the keys, with the key of the current run
+#[derive(Debug, Clone)]
+pub struct Store {
+    path: PathBuf,
+    key: String,
+    entries: Vec<Entry>,
+}
+
+impl Store {
+    /// Read the store from the given file
+    ///
+    /// A missing file is an empty store and invalid lines are skipped.
+    pub fn open(path: &Path, key: &str) -> Result<Self> {
+        let entries = match fs::read_to_string(path) {
+            Ok(contents) => contents.lines().filter_map(Entry::parse).collect(),
+            Err(e) if e.kind() == ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            key: key.to_string(),
+            entries,
+        })
+    }
+
+    /// Write the store to its file
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let contents: String = self
+            .entries
+            .iter()
+            .map(|e| format!("{}\t{}\t{}\t{}\n", e.key, e.count, e.last, e.line))
+            .collect();
+
+        fs::write(&self.path, contents)?;
+
+        Ok(())
+    }
+
+    /// Save the selected lines
+    pub fn record(&mut self, selection: &[Text]) {
+        let lines: Vec<&str> = selection.iter().map(|text| text.output()).collect();
+
+        self.record_at(&lines, now());
+    }
+
+    /// Frecency of each line of the current key
+    pub fn boosts(&self) -> HashMap<String, f32> {
+        let now = now();
+
+        self.entries()
+            .map(|e| (e.line.clone(), e.frecency(now)))
+            .collect()
+    }
+
+    /// Entries of the current key, from higher to lower frecency
+    pub fn ranking(&self) -> Vec<(f32, &Entry)> {
+        let now = now();
+        let mut ranking: Vec<(f32, &Entry)> =
+            self.entries().map(|e| (e.frecency(now), e)).collect();
+
+        ranking.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+        ranking
+    }
+
+    /// Remove the old selections of the current key
+    ///
+    /// It returns the number of removed entries.
+    pub fn prune(&mut self) -> usize {
+        self.prune_at(now())
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(move |e| e.key == self.key)
+    }
+
+    fn record_at(&mut self, lines: &[&str], now: u64) {
+        let key = &self.key;
+
+        for line in lines {
+            let line = normalize(line).into_owned();
+
+            match self
+                .entries
+                .iter_mut()
+                .find(|e| &e.key == key && e.line == line)
+            {
+                Some(entry) => {
+                    entry.count += 1;
+                    entry.last = now;
+                }
+                None => self.entries.push(Entry {
+                    key: key.clone(),
+                    count: 1,
+                    last: now,
+                    line,
+                }),
+            };
+        }
+    }
+
+    fn prune_at(&mut self, now: u64) -> usize {
+        let before = self.entries.len();
+        let key = &self.key;
+
+        self.entries
+            .retain(|e| &e.key != key || now.saturating_sub(e.last) < PRUNE_AGE);
+
+        before - self.entries.len()
+    }
+}
+
+/// Line as it is saved in the store
+///
+/// Lines are saved one per line, so the line breaks of multi-line records become spaces. Look up
+/// the boosts with the normalized line too.
+pub fn normalize(line: &str) -> Cow<'_, str> {
+    if line.contains('\n') {
+        Cow::Owned(line.replace('\n', " "))
+    } else {
+        Cow::Borrowed(line)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(key: &str) -> Store {
+        Store {
+            path: PathBuf::from("selections.tsv"),
+            key: key.to_string(),
+            entries: vec![],
+        }
+    }
+
+    #[test]
+    fn entry_parse_test() {
+        let entry = Entry::parse("git\t3\t100\tmain\tbranch").unwrap();
+
+        assert_eq!(entry.key, "git");
+        assert_eq!(entry.count(), 3);
+        assert_eq!(entry.last, 100);
+        assert_eq!(entry.line(), "main\tbranch");
+
+        assert!(Entry::parse("git\tx\t100\tmain").is_none());
+        assert!(Entry::parse("git\t3\t100").is_none());
+    }
+
+    #[test]
+    fn entry_frecency_test() {
+        let entry = Entry::parse("git\t4\t0\tmain").unwrap();
+
+        assert_eq!(entry.frecency(0), 16.0);
+        assert_eq!(entry.frecency(HOUR), 8.0);
+        assert_eq!(entry.frecency(DAY), 2.0);
+        assert_eq!(entry.frecency(WEEK), 1.0);
+    }
+
+    #[test]
+    fn store_record_test() {
+        let mut store = store("git");
+        store.record_at(&["main", "dev"], 0);
+        store.record_at(&["main"], DAY);
+
+        store.key = "files".to_string();
+        store.record_at(&["main"], DAY);
+
+        let ranking: Vec<(f32, &str)> = store
+            .entries()
+            .map(|e| (e.frecency(DAY), e.line()))
+            .collect();
+        assert_eq!(ranking, vec![(4.0, "main")]);
+
+        store.key = "git".to_string();
+        let ranking: Vec<(f32, &str)> = store
+            .entries()
+            .map(|e| (e.frecency(DAY), e.line()))
+            .collect();
+        assert_eq!(ranking, vec![(8.0, "main"), (0.5, "dev")]);
+    }
+
+    #[test]
+    fn store_record_multiline_test() {
+        let mut store = store("git");
+        store.record_at(&["main\nbranch"], 0);
+
+        let boosts = store.boosts();
+        assert!(boosts.contains_key(normalize("main\nbranch").as_ref()));
+        assert_eq!(normalize("main"), "main");
+    }
+
+    #[test]
+    fn store_prune_test() {
+        let mut store = store("git");
+        store.record_at(&["old"], 0);
+        store.record_at(&["new"], PRUNE_AGE);
+
+        store.key = "files".to_string();
+        store.record_at(&["old"], 0);
+
+        store.key = "git".to_string();
+        assert_eq!(store.prune_at(PRUNE_AGE + 1), 1);
+
+        let lines: Vec<&str> = store.entries.iter().map(|e| e.line()).collect();
+        assert_eq!(lines, vec!["new", "old"]);
+    }
+
+    #[test]
+    fn store_file_test() {
+        let path = std::env::temp_dir()
+            .join(format!("scout-frecency-{}", std::process::id()))
+            .join("selections.tsv");
+
+        let mut store = Store::open(&path, "git").unwrap();
+        store.record_at(&["main"], 10);
+        store.save().unwrap();
+
+        let store = Store::open(&path, "git").unwrap();
+        assert_eq!(
+            store.entries,
+            vec![Entry::parse("git\t1\t10\tmain").unwrap()]
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
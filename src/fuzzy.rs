@@ -3,7 +3,14 @@
 //! The algorithm is a custom port of the [`fuzzaldrin-plus`][fuzzaldrinplus] algorithm.
 //! This is a package used in Atom editor.
 //!
-//! NOTE: The only part missing (I think) from the original algorithm is the path score bonus
+//! The path score bonus from the original algorithm is optional, since not every input
+//! is a list of paths. It can be enabled with `Options::path`.
+//!
+//! On top of the algorithm the query supports an extended syntax (exact, prefix, suffix,
+//! negated and alternative terms). See the `pattern` module for the details.
+//!
+//! When searching on every keystroke, a `Cache` can narrow down the previous results
+//! instead of searching the whole pool again.
 //!
 //! ### References
 //!   * [jeancroy/fuzz-aldrin-plus/src/scorer.coffee#L83](https://github.com/jeancroy/fuzz-aldrin-plus/blob/84eac1d73bacbbd11978e6960f4aa89f8396c540/src/scorer.coffee#L83)
@@ -11,7 +18,9 @@
 //!
 //! [fuzzaldrinplus]: https://github.com/jeancroy/fuzz-aldrin-plus
 
-mod predicates;
+mod cache;
+mod pattern;
+pub(crate) mod predicates;
 mod scoring;
 mod types;
 
@@ -19,9 +28,14 @@ use predicates::*;
 use scoring::*;
 use types::*;
 
-pub use types::{Candidate, Query};
+pub use cache::Cache;
+pub use pattern::Pattern;
+pub use types::{
+    CancelToken, Candidate, Case, Options, ParseCaseError, ParseTiebreakError, Query, Tiebreak,
+};
 
-use crate::common::Text;
+use crate::common::{Text, TextBuilder};
+use crate::frecency;
 use rayon::prelude::*;
 
 // Max number missed consecutive hit = ceil(MISS_COEFF * query.len()) + 5
@@ -30,29 +44,130 @@ const MISS_COEFF: f32 = 0.75;
 /// Search for candidates that fuzzy-match a query
 ///
 /// * If the query is empty it just returns the same pool of candidates
-/// * Otherwise it will try to compute the best match for each candidate
-///   and then sort them from higher score to lower
+/// * Otherwise it will parse the query as a `Pattern`, try to compute the
+///   best match for each candidate and then sort them from higher score to lower.
+///   Candidates with the same score keep the input order
 pub fn search<'pool>(
     q: &str,
     pool: &'pool impl IntoParallelRefIterator<'pool, Item = &'pool Text>,
 ) -> Vec<Candidate> {
+    search_with_options(q, pool, Options::default())
+}
+
+/// Same as `search` but with custom matching `Options`
+pub fn search_with_options<'pool>(
+    q: &str,
+    pool: &'pool impl IntoParallelRefIterator<'pool, Item = &'pool Text>,
+    options: Options,
+) -> Vec<Candidate> {
+    let token = CancelToken::new();
+
+    search_pattern(&Pattern::new(q, options), pool, &token).unwrap_or_default()
+}
+
+/// Search for the candidates that match the pattern
+///
+/// The token is checked for every candidate, if the search is cancelled
+/// it stops right away and returns nothing.
+fn search_pattern<'pool>(
+    pattern: &Pattern,
+    pool: &'pool impl IntoParallelRefIterator<'pool, Item = &'pool Text>,
+    token: &CancelToken,
+) -> Option<Vec<Candidate>> {
     let mut matches: Vec<Candidate>;
+    let options = pattern.options();
 
-    if q.is_empty() {
+    if pattern.is_empty() {
         matches = pool.par_iter().map(|txt| txt.into()).collect();
     } else {
-        let query: Query = q.into();
         matches = pool
             .par_iter()
-            .map(|c| compute_match(&query, &c))
-            .filter(|c| c.is_some())
-            .map(|c| c.unwrap())
+            .map(|c| {
+                if token.is_cancelled() {
+                    None
+                } else {
+                    Some(pattern.compute_match(c))
+                }
+            })
+            .while_some()
+            .flatten()
             .collect();
 
-        matches.par_sort_unstable_by(|a, b| b.cmp(a));
+        if token.is_cancelled() {
+            return None;
+        }
+    }
+
+    if let Some(boosts) = &options.boosts {
+        matches.par_iter_mut().for_each(|candidate| {
+            if let Some(weight) = boosts.get(frecency::normalize(candidate.output()).as_ref()) {
+                candidate.boost(*weight);
+            }
+        });
     }
 
-    matches
+    // Without a query there is nothing to sort, unless some candidates are boosted
+    if options.sort && (!pattern.is_empty() || options.boosts.is_some()) {
+        matches.par_sort_unstable_by(|a, b| options.compare(a, b));
+    } else if !pattern.is_empty() {
+        matches.par_sort_unstable_by_key(|c| c.index());
+    }
+
+    Some(matches)
+}
+
+/// Same as `compute_match` but scoring the subject as a path
+///
+/// The score of the full path is blended with the score of its basename, which means
+/// that matches in the basename will outrank matches in deep directories.
+/// The basename includes as many directories as the query has.
+fn compute_path_match(query: &Query, subject: &Text) -> Option<Candidate> {
+    let candidate = compute_match(query, subject)?;
+
+    // skip trailing separators
+    let mut end = subject.len();
+    while end > 0 && is_path_separator(subject.grapheme_at(end - 1)) {
+        end -= 1;
+    }
+
+    let mut base_position = match last_path_separator(subject, end) {
+        Some(position) => position,
+        // no directories, nothing else to compute
+        None => return Some(candidate),
+    };
+    let file_len = end - base_position - 1;
+
+    let mut depth = count_dir(query, query.len());
+    let mut base_found = true;
+    while depth > 0 {
+        match last_path_separator(subject, base_position) {
+            Some(position) => base_position = position,
+            None => {
+                base_found = false;
+                break;
+            }
+        };
+        depth -= 1;
+    }
+
+    let full_score = candidate.score();
+    let base_score = if base_found {
+        let base_path: String = subject
+            .iter()
+            .skip(base_position + 1)
+            .take(end - base_position - 1)
+            .map(String::as_str)
+            .collect();
+        let base_path = TextBuilder::build(&base_path);
+
+        compute_match(query, &base_path).map_or(0.0, |c| c.score())
+    } else {
+        full_score
+    };
+
+    let score = score_path(full_score, base_score, count_dir(subject, end), file_len);
+
+    Some(Candidate::new(subject, score, candidate.matches))
 }
 
 /// This function will return a Candidate with the computed score and matches.
@@ -125,7 +240,7 @@ fn compute_match(query: &Query, subject: &Text) -> Option<Candidate> {
     //    t | ^ | ^ | d |
     //    x | ^ | ^ | ^ |
     //   ----------------
-    let subject_iter = subject.lowercase_iter().enumerate();
+    let subject_iter = query.subject_iter(subject).enumerate();
     'subject_loop: for (subject_index, subject_grapheme) in subject_iter {
         // for every letter in the subject we move one row in the matrix
 
@@ -149,7 +264,7 @@ fn compute_match(query: &Query, subject: &Text) -> Option<Candidate> {
         let mut record_miss = true;
         should_rebuild = true;
 
-        let query_iter = query.lowercase_iter().enumerate();
+        let query_iter = query.match_iter().enumerate();
         for (query_index, query_grapheme) in query_iter {
             // for every letter in the query we move one column in the matrix
 
@@ -255,13 +370,9 @@ mod tests {
             // Exact match
             ("core".into(), "0core0app.rb", vec![1, 2, 3, 4]),
             // Exact match, second position is better
-            (
-                "core".into(),
-                "0core0app_core.rb".into(),
-                vec![10, 11, 12, 13],
-            ),
+            ("core".into(), "0core0app_core.rb", vec![10, 11, 12, 13]),
             // Consecutive letters
-            ("core".into(), "controller".into(), vec![0, 1, 4, 8]),
+            ("core".into(), "controller", vec![0, 1, 4, 8]),
         ];
 
         for (query, string, expected) in cases {
@@ -0,0 +1,143 @@
+//! Reuse the results of previous searches
+//!
+//! Most of the time a new query is the previous one with an extra letter, which means
+//! that its matches are a subset of the previous matches. Instead of searching the whole
+//! pool again, only the previous matches are scored. Deleting letters brings back queries
+//! that have been searched before, so their results are kept around as well.
+
+use super::{search_pattern, CancelToken, Candidate, Options, Pattern};
+use crate::common::Text;
+use rayon::prelude::*;
+use std::collections::VecDeque;
+
+// Max number of results kept in the cache
+const CACHE_LIMIT: usize = 32;
+
+/// Search results of the latest queries over the same pool of candidates
+///
+/// The cache has to be cleared every time the pool changes.
+#[derive(Debug, Clone, Default)]
+pub struct Cache {
+    options: Options,
+    entries: VecDeque<(Pattern, Vec<Candidate>)>,
+}
+
+impl Cache {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Same as `search_with_options`, but using the previous results when possible
+    ///
+    /// It returns nothing if the search is cancelled with the token.
+    pub fn search<'pool>(
+        &mut self,
+        q: &str,
+        pool: &'pool impl IntoParallelRefIterator<'pool, Item = &'pool Text>,
+        token: &CancelToken,
+    ) -> Option<Vec<Candidate>> {
+        let pattern = Pattern::new(q, self.options.clone());
+
+        if pattern.is_empty() {
+            return search_pattern(&pattern, pool, token);
+        }
+
+        if let Some((_, matches)) = self
+            .entries
+            .iter()
+            .find(|(cached, _)| cached.to_string() == pattern.to_string())
+        {
+            log::trace!("cached results for '{}'", pattern);
+            return Some(matches.clone());
+        }
+
+        let narrowest = self
+            .entries
+            .iter()
+            .filter(|(cached, _)| pattern.narrows(cached))
+            .min_by_key(|(_, matches)| matches.len());
+
+        let matches = match narrowest {
+            Some((cached, matches)) => {
+                log::trace!("narrowing results of '{}' to '{}'", cached, pattern);
+                let texts: Vec<Text> = matches.iter().map(|c| c.text.clone()).collect();
+
+                search_pattern(&pattern, &texts, token)?
+            }
+            None => search_pattern(&pattern, pool, token)?,
+        };
+
+        self.entries.push_back((pattern, matches.clone()));
+        if self.entries.len() > CACHE_LIMIT {
+            let _f = self.entries.pop_front();
+        }
+
+        Some(matches)
+    }
+
+    /// Forget all the previous results
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::TextBuilder;
+
+    fn strings(matches: &[Candidate]) -> Vec<String> {
+        let mut strings: Vec<String> = matches.iter().map(|c| c.to_string()).collect();
+        strings.sort();
+
+        strings
+    }
+
+    #[test]
+    fn cache_search_test() {
+        let pool: Vec<Text> = vec!["foo", "fob", "bar", "foobar"]
+            .into_iter()
+            .map(TextBuilder::build)
+            .collect();
+        let mut cache = Cache::default();
+        let token = CancelToken::new();
+
+        for query in &[
+            "f", "fo", "foo", "foob", "foo", "fo", "^b", "^bar$", "b | o", "",
+        ] {
+            let expected = search_pattern(&Pattern::from(*query), &pool, &token).unwrap();
+            let actual = cache.search(query, &pool, &token).unwrap();
+
+            assert_eq!(strings(&actual), strings(&expected), "Query {:?}", query);
+        }
+
+        // only the narrowed down results are searched
+        let narrowed = cache.search("foobar", &Vec::<Text>::new(), &token);
+        assert_eq!(strings(&narrowed.unwrap()), vec!["foobar"]);
+
+        cache.clear();
+        let cleared = cache.search("foobar", &Vec::<Text>::new(), &token);
+        assert!(cleared.unwrap().is_empty());
+    }
+
+    #[test]
+    fn cache_search_cancelled_test() {
+        let pool: Vec<Text> = vec!["foo", "bar"]
+            .into_iter()
+            .map(TextBuilder::build)
+            .collect();
+        let mut cache = Cache::default();
+        let token = CancelToken::new();
+        token.cancel();
+
+        assert!(cache.search("fo", &pool, &token).is_none());
+
+        // cancelled searches are not cached
+        let token = CancelToken::new();
+        let results = cache.search("fo", &pool, &token).unwrap();
+        assert_eq!(strings(&results), vec!["foo"]);
+    }
+}
@@ -0,0 +1,425 @@
+//! Extended search syntax
+//!
+//! A search query is split by whitespaces into terms and every term must match a candidate.
+//! Each term can be a plain fuzzy query or use one of these operators:
+//!
+//! | Term        | Meaning                                    |
+//! | ----------- | ------------------------------------------ |
+//! | `foo`       | Fuzzy match                                |
+//! | `'foo`      | Exact match, `foo` is somewhere in the text |
+//! | `^foo`      | Exact match at the start of the text       |
+//! | `foo$`      | Exact match at the end of the text         |
+//! | `^foo$`     | The text is exactly `foo`                  |
+//! | `!foo`      | The text doesn't contain `foo`             |
+//! | `!^foo`     | The text doesn't start with `foo`          |
+//! | `!foo$`     | The text doesn't end with `foo`            |
+//! | `foo \| bar` | Either `foo` or `bar` match                |
+//!
+//! A term that is only an operator (like a single `!` or `^`) is treated as a plain fuzzy query.
+
+use super::scoring::*;
+use super::types::*;
+use super::{compute_match, compute_path_match};
+use crate::common::{Text, TextBuilder};
+use std::fmt;
+
+const OR_SEPARATOR: &str = "|";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TermKind {
+    Fuzzy,
+    Exact,
+    Prefix,
+    Suffix,
+    Equal,
+}
+
+/// A single piece of the search pattern
+#[derive(Debug, Clone)]
+struct Term {
+    kind: TermKind,
+    negated: bool,
+    query: Query,
+}
+
+impl Term {
+    fn new(kind: TermKind, negated: bool, text: &str, case: Case) -> Self {
+        let text = TextBuilder::build(text);
+
+        Self {
+            kind,
+            negated,
+            query: Query::new(&text, case),
+        }
+    }
+
+    /// Parse a single token of the extended search syntax
+    fn parse(token: &str, case: Case) -> Self {
+        let mut text = token;
+        let mut negated = false;
+
+        if text.len() > 1 && text.starts_with('!') {
+            negated = true;
+            text = &text[1..];
+        }
+
+        if text.len() > 1 && text.starts_with('\'') {
+            return Self::new(TermKind::Exact, negated, &text[1..], case);
+        }
+
+        if text.len() > 1 && text.starts_with('^') {
+            let text = &text[1..];
+
+            if text.len() > 1 && text.ends_with('$') {
+                return Self::new(TermKind::Equal, negated, &text[..text.len() - 1], case);
+            }
+
+            return Self::new(TermKind::Prefix, negated, text, case);
+        }
+
+        if text.len() > 1 && text.ends_with('$') {
+            return Self::new(TermKind::Suffix, negated, &text[..text.len() - 1], case);
+        }
+
+        // Negating a fuzzy match would discard almost everything,
+        // so a negated plain term is an exact one
+        let kind = if negated {
+            TermKind::Exact
+        } else {
+            TermKind::Fuzzy
+        };
+
+        Self::new(kind, negated, text, case)
+    }
+
+    /// Whether every subject matched by this term is also matched by the other one
+    fn narrows(&self, other: &Term) -> bool {
+        use TermKind::*;
+
+        let text = self.query.to_string();
+        let other_text = other.query.to_string();
+
+        if self.negated || other.negated {
+            return self.negated == other.negated
+                && self.kind == other.kind
+                && self.query.is_case_sensitive() == other.query.is_case_sensitive()
+                && text == other_text;
+        }
+
+        let (text, other_text) = match (
+            self.query.is_case_sensitive(),
+            other.query.is_case_sensitive(),
+        ) {
+            (_, true) if !self.query.is_case_sensitive() => return false,
+            (_, true) => (text, other_text),
+            (_, false) => (text.to_lowercase(), other_text.to_lowercase()),
+        };
+
+        match (other.kind, self.kind) {
+            (Fuzzy, _) | (Exact, Exact) | (Exact, Prefix) | (Exact, Suffix) | (Exact, Equal) => {
+                text.contains(&other_text)
+            }
+            (Prefix, Prefix) | (Prefix, Equal) => text.starts_with(&other_text),
+            (Suffix, Suffix) | (Suffix, Equal) => text.ends_with(&other_text),
+            (Equal, Equal) => text == other_text,
+            _ => false,
+        }
+    }
+
+    /// Compute the score and matches of this term in the given subject
+    ///
+    /// Negated terms never score or highlight anything, they just filter.
+    fn compute(&self, subject: &Text, options: &Options) -> Option<(f32, Vec<usize>)> {
+        let result = match self.kind {
+            TermKind::Fuzzy if options.path => {
+                compute_path_match(&self.query, subject).map(|c| (c.score(), c.matches))
+            }
+            TermKind::Fuzzy => compute_match(&self.query, subject).map(|c| (c.score(), c.matches)),
+            TermKind::Exact => {
+                score_exact_match(&self.query, subject).map(|r| (r.score, r.matches))
+            }
+            TermKind::Prefix => {
+                score_prefix_match(&self.query, subject).map(|r| (r.score, r.matches))
+            }
+            TermKind::Suffix => {
+                score_suffix_match(&self.query, subject).map(|r| (r.score, r.matches))
+            }
+            TermKind::Equal => {
+                if self.query.len() == subject.len() {
+                    score_prefix_match(&self.query, subject).map(|r| (r.score, r.matches))
+                } else {
+                    None
+                }
+            }
+        };
+
+        match (self.negated, result) {
+            (false, result) => result,
+            (true, Some(_)) => None,
+            (true, None) => Some((0.0, vec![])),
+        }
+    }
+}
+
+/// Search pattern parsed from the extended search syntax
+///
+/// All the groups of terms must match a candidate (AND). Inside a group
+/// only one of its terms needs to match (OR).
+#[derive(Debug, Clone, Default)]
+pub struct Pattern {
+    string: String,
+    groups: Vec<Vec<Term>>,
+    options: Options,
+}
+
+impl Pattern {
+    pub fn new(string: &str, options: Options) -> Self {
+        let mut groups: Vec<Vec<Term>> = vec![];
+        let mut join = false;
+
+        for token in string.split_whitespace() {
+            if token == OR_SEPARATOR {
+                join = !groups.is_empty();
+                continue;
+            }
+
+            let term = Term::parse(token, options.case);
+
+            match groups.last_mut() {
+                Some(group) if join => group.push(term),
+                _ => groups.push(vec![term]),
+            }
+
+            join = false;
+        }
+
+        Self {
+            string: string.to_string(),
+            groups,
+            options,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// A pattern without terms matches every candidate
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Whether every subject matched by this pattern is also matched by the other one
+    ///
+    /// This is the case, for example, when a query is extended with more letters.
+    /// Searching a narrower pattern only needs the matches of the wider one.
+    pub fn narrows(&self, other: &Pattern) -> bool {
+        self.options == other.options
+            && other.groups.iter().all(|other_group| {
+                self.groups.iter().any(|group| {
+                    group
+                        .iter()
+                        .all(|term| other_group.iter().any(|other| term.narrows(other)))
+                })
+            })
+    }
+
+    /// Compute the Candidate for the subject, if every group of terms matches it
+    ///
+    /// The score is the sum of each group's best score and the matches are the
+    /// positions highlighted by all of them.
+    ///
+    /// If the subject has a scope only that part of it is matched, but the matches
+    /// are still positions in the whole subject.
+    pub fn compute_match(&self, subject: &Text) -> Option<Candidate> {
+        let mut score = 0.0;
+        let mut matches = vec![];
+
+        let target = match subject.scope() {
+            Some(scope) => &scope.text,
+            None => subject,
+        };
+
+        for group in &self.groups {
+            let (group_score, mut group_matches) = group
+                .iter()
+                .filter_map(|term| term.compute(target, &self.options))
+                .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))?;
+
+            score += group_score;
+            matches.append(&mut group_matches);
+        }
+
+        if let Some(scope) = subject.scope() {
+            matches = matches
+                .into_iter()
+                .map(|position| scope.positions[position])
+                .collect();
+        }
+
+        matches.sort_unstable();
+        matches.dedup();
+
+        Some(Candidate::new(subject, score, matches))
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(string: &str) -> Self {
+        Self::new(string, Options::default())
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::TextBuilder;
+
+    fn kinds(pattern: &Pattern) -> Vec<Vec<(TermKind, bool, String)>> {
+        pattern
+            .groups
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .map(|t| (t.kind, t.negated, t.query.to_string()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pattern_parse_test() {
+        use TermKind::*;
+
+        let cases = vec![
+            ("", vec![]),
+            ("   ", vec![]),
+            ("foo", vec![vec![(Fuzzy, false, "foo")]]),
+            (
+                "foo bar",
+                vec![vec![(Fuzzy, false, "foo")], vec![(Fuzzy, false, "bar")]],
+            ),
+            ("'foo", vec![vec![(Exact, false, "foo")]]),
+            ("^foo", vec![vec![(Prefix, false, "foo")]]),
+            ("foo$", vec![vec![(Suffix, false, "foo")]]),
+            ("^foo$", vec![vec![(Equal, false, "foo")]]),
+            ("!foo", vec![vec![(Exact, true, "foo")]]),
+            ("!^foo", vec![vec![(Prefix, true, "foo")]]),
+            ("!foo$", vec![vec![(Suffix, true, "foo")]]),
+            ("!'foo", vec![vec![(Exact, true, "foo")]]),
+            ("!", vec![vec![(Fuzzy, false, "!")]]),
+            ("^", vec![vec![(Fuzzy, false, "^")]]),
+            ("$", vec![vec![(Fuzzy, false, "$")]]),
+            ("'", vec![vec![(Fuzzy, false, "'")]]),
+            (
+                "foo | bar baz",
+                vec![
+                    vec![(Fuzzy, false, "foo"), (Fuzzy, false, "bar")],
+                    vec![(Fuzzy, false, "baz")],
+                ],
+            ),
+            ("| foo |", vec![vec![(Fuzzy, false, "foo")]]),
+        ];
+
+        for (string, expected) in cases {
+            let pattern = Pattern::from(string);
+            let expected: Vec<Vec<(TermKind, bool, String)>> = expected
+                .into_iter()
+                .map(|group| {
+                    group
+                        .into_iter()
+                        .map(|(k, n, s)| (k, n, s.to_string()))
+                        .collect()
+                })
+                .collect();
+
+            assert_eq!(kinds(&pattern), expected, "Pattern {:?}", string);
+        }
+    }
+
+    #[test]
+    fn pattern_narrows_test() {
+        let cases = vec![
+            ("foo", "fo", true),
+            ("foo", "foo", true),
+            ("fo", "foo", false),
+            ("foo bar", "foo", true),
+            ("foo", "foo bar", false),
+            ("foo b", "fo", true),
+            ("'foo", "fo", true),
+            ("^foo", "'fo", true),
+            ("^foo", "^fo", true),
+            ("foo$", "foo", true),
+            ("foo$x", "foo$", false),
+            ("^foo$", "foo$", true),
+            ("^f", "^", false),
+            ("'f", "'", false),
+            ("foo | b", "foo |", false),
+            ("foo | bar", "fo | ba", true),
+            ("!foo", "!fo", false),
+            ("!foo bar", "!foo", true),
+            ("FOO", "foo", true),
+            ("", "", true),
+            ("foo", "", true),
+            ("", "foo", false),
+        ];
+
+        for (string, other, expected) in cases {
+            let pattern = Pattern::from(string);
+            let other = Pattern::from(other);
+
+            assert_eq!(
+                pattern.narrows(&other),
+                expected,
+                "Expected {:?} to narrow {:?}: {}",
+                string,
+                other.to_string(),
+                expected
+            );
+        }
+
+        let smart = Options {
+            case: Case::Smart,
+            ..Default::default()
+        };
+        assert!(Pattern::new("fooB", smart.clone()).narrows(&Pattern::new("foo", smart.clone())));
+        assert!(!Pattern::new("foo", smart.clone()).narrows(&Pattern::new("Fo", smart)));
+    }
+
+    #[test]
+    fn pattern_compute_match_test() {
+        let cases = vec![
+            ("src rs", "src/main.rs", Some(vec![0, 1, 2, 9, 10])),
+            ("'main", "src/main.rs", Some(vec![4, 5, 6, 7])),
+            ("^src .rs$", "src/main.rs", Some(vec![0, 1, 2, 8, 9, 10])),
+            ("^main", "src/main.rs", None),
+            ("main$", "src/main.rs", None),
+            ("^main.rs$", "main.rs", Some(vec![0, 1, 2, 3, 4, 5, 6])),
+            ("^main$", "main.rs", None),
+            ("!test", "src/main.rs", Some(vec![])),
+            ("!main", "src/main.rs", None),
+            ("main !^test", "src/main.rs", Some(vec![4, 5, 6, 7])),
+            ("'lib | 'main", "src/main.rs", Some(vec![4, 5, 6, 7])),
+            ("'lib | 'test", "src/main.rs", None),
+        ];
+
+        for (string, subject, expected) in cases {
+            let pattern = Pattern::from(string);
+            let subject = TextBuilder::build(subject);
+            let result = pattern.compute_match(&subject).map(|c| c.matches);
+
+            assert_eq!(
+                result, expected,
+                "Expected {} to match {} at {:?}",
+                pattern, subject, expected
+            );
+        }
+    }
+}
@@ -5,6 +5,7 @@ use crate::common::Text;
 use std::collections::HashSet;
 
 const ACRONYM_FREQUENCY: usize = 12;
+const PATH_SEPARATOR: &str = "/";
 
 lazy_static! {
     static ref WORD_SEPARATORS: HashSet<&'static str> = {
@@ -36,8 +37,8 @@ lazy_static! {
 
 /// Check whether a query is inside a subject or not
 pub fn is_match(query: &Query, subject: &Text) -> bool {
-    let query_iter = query.lowercase_iter().filter(|g| !is_optional(&g));
-    let mut subject_iter = subject.lowercase_iter();
+    let query_iter = query.match_iter().filter(|g| !is_optional(g));
+    let mut subject_iter = query.subject_iter(subject);
 
     let mut query_count = 0;
     let mut subject_count = 0;
@@ -49,7 +50,7 @@ pub fn is_match(query: &Query, subject: &Text) -> bool {
             break 'query_loop;
         }
 
-        'subject_loop: while let Some(subject_grapheme) = subject_iter.next() {
+        'subject_loop: for subject_grapheme in subject_iter.by_ref() {
             subject_count += 1;
 
             if query_grapheme == subject_grapheme {
@@ -141,6 +142,40 @@ pub fn is_word_separator(grapheme: &str) -> bool {
     WORD_SEPARATORS.contains(grapheme)
 }
 
+/// Check whether the given grapheme is a path separator
+pub fn is_path_separator(grapheme: &str) -> bool {
+    grapheme == PATH_SEPARATOR
+}
+
+/// Count the number of directories in the text until the given position (not included)
+///
+/// Separators at the start of the text and consecutive separators are ignored,
+/// so `foo/bar`, `/foo/bar` and `foo//bar` have the same depth.
+pub fn count_dir(text: &Text, end: usize) -> usize {
+    let mut count = 0;
+    let graphemes = text.iter().take(end).skip_while(|g| is_path_separator(g));
+    let mut prev_is_separator = false;
+
+    for grapheme in graphemes {
+        let is_separator = is_path_separator(grapheme);
+
+        if is_separator && !prev_is_separator {
+            count += 1;
+        }
+
+        prev_is_separator = is_separator;
+    }
+
+    count
+}
+
+/// Get the position of the last path separator before the given position (not included), if any
+pub fn last_path_separator(text: &Text, end: usize) -> Option<usize> {
+    (0..end)
+        .rev()
+        .find(|&index| is_path_separator(text.grapheme_at(index)))
+}
+
 fn is_optional(grapheme: &str) -> bool {
     OPTIONAL_GRAPHEMES.contains(grapheme)
 }
@@ -182,6 +217,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn is_match_with_case_test() {
+        let cases = vec![
+            ("foo", "FOO", Case::Smart, true),
+            ("Foo", "foo", Case::Smart, false),
+            ("Foo", "xFoo", Case::Smart, true),
+            ("FoO", "foo", Case::Ignore, true),
+            ("foo", "FOO", Case::Respect, false),
+            ("foo", "fXoXo", Case::Respect, true),
+            ("Y̆公", "y̆公", Case::Smart, false),
+            ("Y̆公", "Y̆x公", Case::Smart, true),
+        ];
+
+        for (q, s, case, expected) in cases {
+            let query = Query::new(&TextBuilder::build(q), case);
+            let subject = TextBuilder::build(s);
+
+            assert_eq!(
+                is_match(&query, &subject),
+                expected,
+                "Query {} ({:?}). Subject {}",
+                query,
+                case,
+                subject
+            );
+        }
+    }
+
     #[test]
     fn is_a_unique_acronym_test() {
         let cases = vec![
@@ -258,6 +321,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn count_dir_test() {
+        let cases = vec![
+            ("foo", 3, 0),
+            ("foo/bar", 7, 1),
+            ("/foo/bar", 8, 1),
+            ("foo//bar", 8, 1),
+            ("foo/bar/", 8, 2),
+            ("foo/bar/baz", 7, 1),
+            ("y̆/公/🍣", 5, 2),
+            ("", 0, 0),
+        ];
+
+        for (string, end, expected) in cases {
+            let text = TextBuilder::build(string);
+
+            assert_eq!(
+                count_dir(&text, end),
+                expected,
+                "Expected {} to have {:?} directories until {:?}",
+                text,
+                expected,
+                end
+            );
+        }
+    }
+
+    #[test]
+    fn last_path_separator_test() {
+        let cases = vec![
+            ("foo", 3, None),
+            ("foo/bar", 7, Some(3)),
+            ("foo/bar/baz", 7, Some(3)),
+            ("foo/bar/baz", 11, Some(7)),
+            ("/foo", 4, Some(0)),
+            ("y̆/公/🍣", 5, Some(3)),
+        ];
+
+        for (string, end, expected) in cases {
+            let text = TextBuilder::build(string);
+
+            assert_eq!(
+                last_path_separator(&text, end),
+                expected,
+                "Expected {} to have its last separator before {:?} at {:?}",
+                text,
+                end,
+                expected
+            );
+        }
+    }
+
     #[test]
     fn is_word_separator_test() {
         let cases = vec![
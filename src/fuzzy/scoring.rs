@@ -11,6 +11,10 @@ const POSITION_BONUS: f32 = 20.0;
 const POSITION_MIN: f32 = 0.0;
 // Full path length at which the whole match score is halved.
 const TAU_SIZE: f32 = 150.0;
+// Directory depth at which the full path influence is halved.
+const TAU_DEPTH: f32 = 20.0;
+// Penalty applied to long basenames (filenames)
+const FILE_COEFF: f32 = 2.5;
 
 /// Given a qualified score (quality), calculate how good it is based on query's
 /// and subject's length and position
@@ -37,6 +41,19 @@ pub fn score_size(query_len: usize, subject_len: usize) -> f32 {
     TAU_SIZE / (TAU_SIZE + penalty as f32)
 }
 
+/// Blend the score of the full path with the score of its basename
+///
+/// For paths with few directories the basename score dominates, the deeper the
+/// path the more the full path score counts. A penalty based on the size of the
+/// basename is applied to the full path score, so focused basename matches can
+/// overcome longer directories.
+pub fn score_path(full_score: f32, base_score: f32, depth: usize, file_len: usize) -> f32 {
+    let alpha = 0.5 * TAU_DEPTH / (TAU_DEPTH + depth as f32);
+    let file_size = TAU_SIZE / (TAU_SIZE + FILE_COEFF * file_len as f32);
+
+    alpha * base_score + (1.0 - alpha) * full_score * file_size
+}
+
 /// Calculate the score of the acronyms represented by the query, if any
 pub fn score_acronyms(query: &Query, subject: &Text) -> Option<AcronymResult> {
     // single char strings are not an acronym
@@ -50,8 +67,8 @@ pub fn score_acronyms(query: &Query, subject: &Text) -> Option<AcronymResult> {
     let mut sum_position = 0;
     let mut same_case = 0;
 
-    let query_iter = query.lowercase_iter().enumerate();
-    let mut subject_iter = subject.lowercase_iter().enumerate();
+    let query_iter = query.match_iter().enumerate();
+    let mut subject_iter = query.subject_iter(subject).enumerate();
 
     let mut progress = 0;
     'query_loop: for (qindex, query_grapheme) in query_iter {
@@ -60,7 +77,7 @@ pub fn score_acronyms(query: &Query, subject: &Text) -> Option<AcronymResult> {
             break 'query_loop;
         }
 
-        'subject_loop: while let Some((index, subject_grapheme)) = subject_iter.next() {
+        'subject_loop: for (index, subject_grapheme) in subject_iter.by_ref() {
             progress += 1;
 
             if query_grapheme == subject_grapheme {
@@ -121,34 +138,43 @@ pub fn score_acronyms(query: &Query, subject: &Text) -> Option<AcronymResult> {
 pub fn score_exact_match(query: &Query, subject: &Text) -> Option<ExactMatchResult> {
     let (mut position, mut same_case) = sequence_position(query, subject, 0)?;
 
-    let mut is_start;
-    is_start = is_start_of_word(subject, position);
-
-    if !is_start {
+    if !is_start_of_word(subject, position) {
         // try a second sequence to see if is better (word start) than the previous one
         // we don't want to try more than twice
         if let Some((sec_position, sec_same_case)) =
             sequence_position(query, subject, position + query.len())
         {
-            is_start = is_start_of_word(subject, sec_position);
-
-            if is_start {
+            if is_start_of_word(subject, sec_position) {
                 position = sec_position;
                 same_case = sec_same_case;
             }
         }
     }
 
-    let is_end = is_end_of_word(subject, (position + query.len()) - 1);
-    let score = score_quality(
-        query.len(),
-        subject.len(),
-        score_pattern(query.len(), query.len(), same_case, is_start, is_end),
-        position as f32,
-    );
-    let matches: Vec<usize> = (position..(position + query.len())).collect();
+    Some(score_sequence(query, subject, position, same_case))
+}
+
+/// Calculate the score of an exact match at the start of the subject, if any
+pub fn score_prefix_match(query: &Query, subject: &Text) -> Option<ExactMatchResult> {
+    let (position, same_case) = sequence_position(query, subject, 0)?;
 
-    Some(ExactMatchResult::new(score, matches))
+    if position != 0 {
+        return None;
+    }
+
+    Some(score_sequence(query, subject, position, same_case))
+}
+
+/// Calculate the score of an exact match at the end of the subject, if any
+pub fn score_suffix_match(query: &Query, subject: &Text) -> Option<ExactMatchResult> {
+    if query.len() > subject.len() {
+        return None;
+    }
+
+    let skip = subject.len() - query.len();
+    let (position, same_case) = sequence_position(query, subject, skip)?;
+
+    Some(score_sequence(query, subject, position, same_case))
 }
 
 /// Shared logic to calculate scores in different scenarios:
@@ -224,9 +250,9 @@ pub fn score_consecutives(
         same_case += 1;
     }
 
-    let query_iter = query.lowercase_iter().enumerate().skip(query_position + 1);
-    let mut subject_iter = subject
-        .lowercase_iter()
+    let query_iter = query.match_iter().enumerate().skip(query_position + 1);
+    let mut subject_iter = query
+        .subject_iter(subject)
         .enumerate()
         .skip(subject_position + 1);
 
@@ -287,11 +313,31 @@ pub fn score_character(
     position_score + (WM * (score + start_bonus))
 }
 
+/// Calculate the score of the exact sequence of Query found in Subject at the given position
+fn score_sequence(
+    query: &Query,
+    subject: &Text,
+    position: usize,
+    same_case: usize,
+) -> ExactMatchResult {
+    let is_start = is_start_of_word(subject, position);
+    let is_end = is_end_of_word(subject, (position + query.len()) - 1);
+    let score = score_quality(
+        query.len(),
+        subject.len(),
+        score_pattern(query.len(), query.len(), same_case, is_start, is_end),
+        position as f32,
+    );
+    let matches: Vec<usize> = (position..(position + query.len())).collect();
+
+    ExactMatchResult::new(score, matches)
+}
+
 /// Get the position of the exact sequence of Query contained in Subject, if any
 /// It also returns the number of same case graphemes in the sequence
 fn sequence_position(query: &Query, subject: &Text, skip: usize) -> Option<(usize, usize)> {
-    let mut query_iter = query.lowercase_iter().enumerate();
-    let mut subject_iter = subject.lowercase_iter().enumerate().skip(skip);
+    let mut query_iter = query.match_iter().enumerate();
+    let mut subject_iter = query.subject_iter(subject).enumerate().skip(skip);
 
     let mut sequence = false;
     let mut position = 0;
@@ -310,11 +356,17 @@ fn sequence_position(query: &Query, subject: &Text, skip: usize) -> Option<(usiz
                 same_case += 1
             }
         } else {
+            if sequence {
+                // the sequence broke in the middle, start again right after
+                // where it started so overlapping sequences are not skipped
+                subject_iter = query.subject_iter(subject).enumerate().skip(position + 1);
+            }
+
             same_case = 0;
             sequence = false;
 
             // rewind the iterator
-            query_iter = query.lowercase_iter().enumerate();
+            query_iter = query.match_iter().enumerate();
         }
     }
 
@@ -430,6 +482,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn score_prefix_match_test() {
+        let cases = vec![
+            ("src", "src/main.rs", Some(vec![0, 1, 2])),
+            ("SRC", "src/main.rs", Some(vec![0, 1, 2])),
+            ("y̆公", "y̆公🍣.js", Some(vec![0, 1])),
+            ("main", "src/main.rs", None),
+            ("src/main.rs/", "src/main.rs", None),
+        ];
+
+        for (q, s, expected) in cases {
+            let query = Query::from(q);
+            let subject = TextBuilder::build(s);
+            let result = score_prefix_match(&query, &subject).map(|r| r.matches);
+
+            assert_eq!(
+                result, expected,
+                "Expected {} to be a prefix of {} at {:?}",
+                query, subject, expected
+            );
+        }
+    }
+
+    #[test]
+    fn score_suffix_match_test() {
+        let cases = vec![
+            (".rs", "src/main.rs", Some(vec![8, 9, 10])),
+            (".RS", "src/main.rs", Some(vec![8, 9, 10])),
+            ("🍣", "y̆公🍣", Some(vec![2])),
+            ("main", "src/main.rs", None),
+            ("/src/main.rs", "src/main.rs", None),
+        ];
+
+        for (q, s, expected) in cases {
+            let query = Query::from(q);
+            let subject = TextBuilder::build(s);
+            let result = score_suffix_match(&query, &subject).map(|r| r.matches);
+
+            assert_eq!(
+                result, expected,
+                "Expected {} to be a suffix of {} at {:?}",
+                query, subject, expected
+            );
+        }
+    }
+
     #[test]
     fn score_consecutives_test() {
         let cases = vec![
@@ -476,6 +574,8 @@ mod tests {
             ("🍣", "y̆公🍣", 0, Some((2, 1))),
             ("foo", "fooxfoo", 2, Some((4, 3))),
             ("foo", "xfoo", 2, None),
+            ("ab", "aab", 0, Some((1, 2))),
+            ("aab", "aaab", 0, Some((1, 3))),
             ("foo", "foxo", 0, None),
             ("foo", "nope", 0, None),
         ];
@@ -1,10 +1,174 @@
 //! Basic types used through scoring functions
 
 use crate::common::{Text, TextBuilder};
+use serde::Deserialize;
 use std::cmp::Ordering;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
 use std::fmt;
 use std::ops::Deref;
+use std::slice::Iter;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub struct ParseCaseError;
+
+impl fmt::Display for ParseCaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing case mode")
+    }
+}
+
+impl Error for ParseCaseError {}
+
+/// How the case of the graphemes is taken into account while matching
+///
+/// The case always affects the score, this only controls whether
+/// candidates with a different case are rejected or not.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Case {
+    /// Case sensitive only if the query has uppercase graphemes
+    #[serde(rename = "smart")]
+    Smart,
+    /// Always case insensitive
+    #[serde(rename = "ignore")]
+    #[default]
+    Ignore,
+    /// Always case sensitive
+    #[serde(rename = "respect")]
+    Respect,
+}
+
+impl FromStr for Case {
+    type Err = ParseCaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "smart" => Ok(Self::Smart),
+            "ignore" => Ok(Self::Ignore),
+            "respect" => Ok(Self::Respect),
+            _ => Err(ParseCaseError),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseTiebreakError;
+
+impl fmt::Display for ParseTiebreakError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing tiebreak criteria")
+    }
+}
+
+impl Error for ParseTiebreakError {}
+
+/// Criteria to sort candidates
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Tiebreak {
+    /// Higher score first
+    #[serde(rename = "score")]
+    Score,
+    /// Shorter text first
+    #[serde(rename = "length")]
+    Length,
+    /// Matches closer to the beginning first
+    #[serde(rename = "begin")]
+    Begin,
+    /// Matches closer to the end first
+    #[serde(rename = "end")]
+    End,
+    /// Input order
+    #[serde(rename = "index")]
+    Index,
+}
+
+impl Tiebreak {
+    /// Parse a comma separated list of criteria
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, ParseTiebreakError> {
+        s.split(',')
+            .map(|criteria| criteria.trim().parse())
+            .collect()
+    }
+
+    /// Compare two candidates, the one that should go first is the lesser
+    pub fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+        match self {
+            // Only the scores, the input order is the last criteria of all
+            Self::Score => b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal),
+            Self::Length => a.len().cmp(&b.len()),
+            Self::Begin => a.first_match().cmp(&b.first_match()),
+            Self::End => a.distance_to_end().cmp(&b.distance_to_end()),
+            Self::Index => a.index().cmp(&b.index()),
+        }
+    }
+}
+
+impl FromStr for Tiebreak {
+    type Err = ParseTiebreakError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "score" => Ok(Self::Score),
+            "length" => Ok(Self::Length),
+            "begin" => Ok(Self::Begin),
+            "end" => Ok(Self::End),
+            "index" => Ok(Self::Index),
+            _ => Err(ParseTiebreakError),
+        }
+    }
+}
+
+/// Options that change how candidates are matched, scored and sorted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Treat candidates as file paths, favouring matches in the basename
+    /// over matches in deep directories
+    pub path: bool,
+    /// Case sensitivity mode
+    pub case: Case,
+    /// Criteria to sort candidates with the same score. The score goes first
+    /// unless it is part of the criteria and the input order always goes last
+    pub tiebreak: Vec<Tiebreak>,
+    /// Sort candidates at all, otherwise they keep the input order
+    pub sort: bool,
+    /// Extra weight of some candidates (by their output), like how often
+    /// and how recently they were selected
+    pub boosts: Option<Arc<HashMap<String, f32>>>,
+}
+
+impl Options {
+    /// Compare two candidates following the tiebreak criteria
+    pub fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+        let score = if self.tiebreak.contains(&Tiebreak::Score) {
+            None
+        } else {
+            Some(&Tiebreak::Score)
+        };
+
+        score
+            .into_iter()
+            .chain(self.tiebreak.iter())
+            .chain(std::iter::once(&Tiebreak::Index))
+            .fold(Ordering::Equal, |ord, criteria| {
+                ord.then_with(|| criteria.compare(a, b))
+            })
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            path: false,
+            case: Case::default(),
+            tiebreak: vec![],
+            sort: true,
+            boosts: None,
+        }
+    }
+}
 
 /// Search query representation.
 ///
@@ -13,19 +177,66 @@ use std::ops::Deref;
 pub struct Query {
     text: Text,
     set: HashSet<String>,
+    case: Case,
+    sensitive: bool,
 }
 
 impl Query {
-    pub fn new(text: &Text) -> Self {
+    pub fn new(text: &Text, case: Case) -> Self {
         let text = text.clone();
-        let set = text.lowercase_iter().cloned().collect();
+        let sensitive = match case {
+            Case::Ignore => false,
+            Case::Respect => true,
+            Case::Smart => text
+                .iter()
+                .zip(text.lowercase_iter())
+                .any(|(grapheme, lowercase)| grapheme != lowercase),
+        };
+
+        let set = if sensitive {
+            text.iter().cloned().collect()
+        } else {
+            text.lowercase_iter().cloned().collect()
+        };
 
-        Self { text, set }
+        Self {
+            text,
+            set,
+            case,
+            sensitive,
+        }
     }
 
     pub fn contains(&self, grapheme: &str) -> bool {
         self.set.contains(grapheme)
     }
+
+    pub fn case(&self) -> Case {
+        self.case
+    }
+
+    /// Whether the query only matches graphemes with the same case
+    pub fn is_case_sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    /// Graphemes of the query to compare with the ones from a subject
+    ///
+    /// They are lowercase unless the query is case sensitive
+    pub fn match_iter(&self) -> Iter<'_, String> {
+        self.subject_iter(&self.text)
+    }
+
+    /// Graphemes of a subject to compare with the ones from the query
+    ///
+    /// They are lowercase unless the query is case sensitive
+    pub fn subject_iter<'s>(&self, subject: &'s Text) -> Iter<'s, String> {
+        if self.sensitive {
+            subject.iter()
+        } else {
+            subject.lowercase_iter()
+        }
+    }
 }
 
 impl Deref for Query {
@@ -40,7 +251,7 @@ impl From<&str> for Query {
     fn from(string: &str) -> Self {
         let text = TextBuilder::build(string);
 
-        Self::new(&text)
+        Self::new(&text, Case::default())
     }
 }
 
@@ -48,7 +259,7 @@ impl From<String> for Query {
     fn from(string: String) -> Self {
         let text = TextBuilder::build(&string);
 
-        Self::new(&text)
+        Self::new(&text, Case::default())
     }
 }
 
@@ -58,6 +269,26 @@ impl fmt::Display for Query {
     }
 }
 
+/// Flag to abort a search that is no longer needed
+///
+/// Clones share the same flag, so a search can be cancelled from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, AtomicOrdering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(AtomicOrdering::Relaxed)
+    }
+}
+
 /// A string that fuzzy-matches a query.
 #[derive(Debug, Clone)]
 pub struct Candidate {
@@ -80,6 +311,37 @@ impl Candidate {
     pub fn score(&self) -> f32 {
         self.score
     }
+
+    /// Raise the score with the given weight
+    ///
+    /// The score grows logarithmically with the weight, so a habitual pick
+    /// can't outrank much better matches. Candidates without a score (empty
+    /// queries) are ranked by the weight alone.
+    pub fn boost(&mut self, weight: f32) {
+        let bonus = weight.max(0.0).ln_1p();
+
+        if self.score > 0.0 {
+            self.score *= 1.0 + bonus;
+        } else {
+            self.score += bonus;
+        }
+    }
+
+    /// Position of the line in the input
+    pub fn index(&self) -> usize {
+        self.text.index()
+    }
+
+    fn first_match(&self) -> usize {
+        self.matches.first().copied().unwrap_or(0)
+    }
+
+    fn distance_to_end(&self) -> usize {
+        match self.matches.last() {
+            Some(last) => self.len() - last,
+            None => self.len(),
+        }
+    }
 }
 
 impl Deref for Candidate {
@@ -102,9 +364,12 @@ impl fmt::Display for Candidate {
     }
 }
 
+/// Candidates are ordered by score and the ones with the same score
+/// by input order, with the first lines being greater
 impl Ord for Candidate {
     fn cmp(&self, other: &Candidate) -> Ordering {
-        match self.partial_cmp(other) {
+        match self.score.partial_cmp(&other.score) {
+            Some(Ordering::Equal) => other.index().cmp(&self.index()),
             Some(ord) => ord,
             // let's just assume that if two subject's can't be compared
             // they are equal
@@ -115,7 +380,7 @@ impl Ord for Candidate {
 
 impl PartialOrd for Candidate {
     fn partial_cmp(&self, other: &Candidate) -> Option<Ordering> {
-        self.score.partial_cmp(&other.score)
+        Some(self.cmp(other))
     }
 }
 
@@ -123,7 +388,7 @@ impl Eq for Candidate {}
 
 impl PartialEq for Candidate {
     fn eq(&self, other: &Candidate) -> bool {
-        self.score == other.score
+        self.cmp(other) == Ordering::Equal
     }
 }
 
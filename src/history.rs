@@ -0,0 +1,248 @@
+//! Query history
+//!
+//! Accepted queries are appended to a history file, one per line, so they can be recalled in
+//! later runs. While the history is browsed the prompt shows the recalled query, and going past
+//! the latest entry brings back the query that was being typed.
+
+use crate::common::Result;
+use crate::config::components::HistoryConfig;
+use async_std::fs;
+use async_std::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Previous queries and the position of the one being recalled
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    path: Option<PathBuf>,
+    size: usize,
+    dedup: bool,
+    entries: Vec<String>,
+    position: usize,
+    draft: String,
+}
+
+impl History {
+    pub fn new(config: &HistoryConfig) -> Self {
+        Self {
+            path: config.file().map(PathBuf::from),
+            size: config.size(),
+            dedup: config.dedup(),
+            ..Default::default()
+        }
+    }
+
+    /// Read the previous queries from the history file
+    ///
+    /// A missing file is an empty history.
+    pub async fn load(&mut self) -> Result<()> {
+        if let Some(path) = &self.path {
+            if path.exists() {
+                self.entries = read(path).await?;
+                self.truncate();
+            }
+        }
+
+        self.position = self.entries.len();
+
+        Ok(())
+    }
+
+    /// Append the query to the history file
+    ///
+    /// Other sessions might be appending queries to the same file, so it is only rewritten
+    /// with its latest queries once it has more than the max number of them.
+    pub async fn save(&mut self, query: &str) -> Result<()> {
+        let query = match self.push(query) {
+            Some(query) => query,
+            None => return Ok(()),
+        };
+
+        if let Some(path) = &self.path {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(format!("{}\n", query).as_bytes()).await?;
+            file.flush().await?;
+
+            let mut entries = read(path).await?;
+            if entries.len() > self.size {
+                entries.drain(..entries.len() - self.size);
+
+                let mut contents = entries.join("\n");
+                contents.push('\n');
+                fs::write(path, contents).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Query before the one being recalled
+    ///
+    /// The current query is kept to come back to it with `newer`
+    pub fn older(&mut self, current: &str) -> Option<String> {
+        if self.position == 0 {
+            return None;
+        }
+
+        if self.position == self.entries.len() {
+            self.draft = current.to_string();
+        }
+
+        self.position -= 1;
+
+        Some(self.entries[self.position].clone())
+    }
+
+    /// Query after the one being recalled
+    pub fn newer(&mut self) -> Option<String> {
+        if self.position >= self.entries.len() {
+            return None;
+        }
+
+        self.position += 1;
+
+        match self.entries.get(self.position) {
+            Some(entry) => Some(entry.clone()),
+            None => Some(self.draft.clone()),
+        }
+    }
+
+    /// Add the query to the entries, it returns the query if it has to be saved
+    fn push(&mut self, query: &str) -> Option<String> {
+        // Queries are saved one per line
+        let query = query.replace('\n', " ");
+        if query.is_empty() {
+            return None;
+        }
+
+        let saved = if self.dedup && self.entries.last() == Some(&query) {
+            None
+        } else {
+            self.entries.push(query.clone());
+            Some(query)
+        };
+
+        self.truncate();
+        self.position = self.entries.len();
+
+        saved
+    }
+
+    // Only the latest entries are kept
+    fn truncate(&mut self) {
+        if self.entries.len() > self.size {
+            let excess = self.entries.len() - self.size;
+            self.entries.drain(..excess);
+        }
+    }
+}
+
+async fn read(path: &Path) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(path).await?;
+    let entries = contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::task;
+
+    fn build(size: usize, dedup: bool) -> History {
+        let mut config = HistoryConfig::default();
+        config.set_size(size);
+        config.set_dedup(dedup);
+
+        History::new(&config)
+    }
+
+    #[test]
+    fn history_browse_test() {
+        let mut history = build(10, true);
+        for query in &["foo", "bar", "bar", "", "baz"] {
+            history.push(query);
+        }
+
+        assert_eq!(history.newer(), None);
+        assert_eq!(history.older("qu"), Some("baz".to_string()));
+        assert_eq!(history.older("baz"), Some("bar".to_string()));
+        assert_eq!(history.older("bar"), Some("foo".to_string()));
+        assert_eq!(history.older("foo"), None);
+        assert_eq!(history.newer(), Some("bar".to_string()));
+        assert_eq!(history.newer(), Some("baz".to_string()));
+        assert_eq!(history.newer(), Some("qu".to_string()));
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn history_size_and_dedup_test() {
+        let mut history = build(3, false);
+        for query in &["a", "b", "b", "c"] {
+            history.push(query);
+        }
+
+        assert_eq!(history.entries, vec!["b", "b", "c"]);
+
+        let mut history = build(3, true);
+        for query in &["a", "b", "b", "a", "c"] {
+            history.push(query);
+        }
+
+        assert_eq!(history.entries, vec!["b", "a", "c"]);
+    }
+
+    #[test]
+    fn history_file_test() {
+        let path = std::env::temp_dir().join(format!("scout-history-{}", std::process::id()));
+        let mut config = HistoryConfig::default();
+        config.set_file(path.to_str().unwrap());
+
+        task::block_on(async {
+            let mut history = History::new(&config);
+            history.load().await.unwrap();
+            history.save("foo").await.unwrap();
+            history.save("bar").await.unwrap();
+
+            let mut history = History::new(&config);
+            history.load().await.unwrap();
+            assert_eq!(history.older(""), Some("bar".to_string()));
+            assert_eq!(history.older(""), Some("foo".to_string()));
+        });
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn history_file_many_sessions_test() {
+        let path = std::env::temp_dir().join(format!("scout-sessions-{}", std::process::id()));
+        let mut config = HistoryConfig::default();
+        config.set_file(path.to_str().unwrap());
+        config.set_size(3);
+
+        task::block_on(async {
+            let mut first = History::new(&config);
+            first.load().await.unwrap();
+            let mut second = History::new(&config);
+            second.load().await.unwrap();
+
+            first.save("a").await.unwrap();
+            second.save("b").await.unwrap();
+            first.save("c").await.unwrap();
+            assert_eq!(read(&path).await.unwrap(), vec!["a", "b", "c"]);
+
+            // Over the max size only the latest queries of all the sessions are kept
+            second.save("d").await.unwrap();
+            assert_eq!(read(&path).await.unwrap(), vec!["b", "c", "d"]);
+        });
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -4,16 +4,26 @@ extern crate lazy_static;
 
 #[macro_use]
 mod macros;
+pub mod ansi;
 pub mod common;
 pub mod config;
 pub mod data_input;
 pub mod engine;
 pub mod events;
+pub mod fields;
+pub mod filter;
+pub mod frecency;
 pub mod fuzzy;
+pub mod history;
 pub mod person_input;
+pub mod preview;
+pub mod process;
 pub mod ptty;
+pub mod resize;
 pub mod screen;
+pub mod source;
 pub mod state;
 pub mod supervisor;
 pub mod terminal_size;
 pub mod ui;
+pub mod walker;
@@ -5,18 +5,26 @@ extern crate clap;
 
 use async_std::io;
 use async_std::os::unix::io::AsRawFd;
+use async_std::sync::Arc;
 use async_std::task;
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
 use std::convert::TryFrom;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
 use std::process;
 
 use scout::common::{Result, Text};
-use scout::config::Configurator;
+use scout::config::{keys, Config, Configurator};
+use scout::fields::Fields;
+use scout::filter;
+use scout::frecency::Store;
+use scout::fuzzy::Tiebreak;
 use scout::ptty::{self, PTTY};
 use scout::supervisor;
+use scout::walker;
 
 const EXTENDED_HELP: &str = r#"SUPPORTED KEYS:
-    - Enter to select the current highlighted match and print it to STDOUT
+    - Enter to select the current highlighted match (or the marked ones) and print it to STDOUT
     - ^u to clear the prompt
     - ^n or Down arrow key to select the next match
     - ^p or Up arrow key to select the previous match
@@ -24,7 +32,26 @@ const EXTENDED_HELP: &str = r#"SUPPORTED KEYS:
     - ^a to go to the beginning of the prompt
     - Left arrow key to move the cursor to the left in the prompt
     - Right arrow key to move the cursor to the right in the prompt
+    - Alt-b and Alt-f to move the cursor a word to the left and to the right
+    - ^w to delete the previous word and ^k to delete until the end of the prompt
+    - ^y to paste the last deleted text
+    - Delete or ^d to delete the character under the cursor
+    - ^_ to undo the last change in the prompt
+    - ^r or Alt-p to recall the previous query, Alt-n the next one (with --history)
     - ESC to quit without selecting a match
+    - Tab to mark the current match and move down (with --multi)
+    - Shift-Tab to mark the current match and move up (with --multi)
+    - Alt-h and Alt-l to scroll a long selection to the left and to the right
+    Keys can be changed in the [keys] config section or with --bind
+
+SEARCH SYNTAX:
+    Space separated terms must all match. Each term can be:
+    - foo to fuzzy match foo
+    - 'foo to match exactly foo
+    - ^foo to match lines starting with foo
+    - foo$ to match lines ending with foo
+    - !foo to match lines not containing foo
+    - foo | bar to match either foo or bar
 
 EXAMPLES:
     $ find * -type f | scout
@@ -35,6 +62,57 @@ EXAMPLES:
     # Use a custom config file
     $ find * -type f | scout --config="./config.toml"
 
+    # Rank matches in file names higher than in directories
+    $ git ls-files | scout --path
+
+    # Prefer shorter paths when scores are equal
+    $ git ls-files | scout --tiebreak=length
+
+    # Filter the most recent branches without changing their order
+    $ git branch --sort=-committerdate | scout --no-sort
+
+    # Match case only when the query has uppercase letters
+    $ git ls-files | scout --case=smart
+
+    # Only match the commit message, but print the whole line
+    $ git log --oneline | scout --nth=2..
+
+    # Show branches with their last commit, but only print the branch name
+    $ git branch --format='%(refname:short) %(subject)' | scout --accept-nth=1
+
+    # Print the 10 best matches without the interface, e.g. in scripts
+    $ git ls-files | scout --filter=main --limit=10
+
+    # Mark many files and open all of them
+    $ git ls-files | scout --multi | xargs -o vim
+
+    # Pick a file under the current directory, skipping the ignored ones
+    $ scout --type=f --exclude='*.lock'
+
+    # List the files of the repository when nothing is piped to scout
+    $ SCOUT_DEFAULT_COMMAND='git ls-files' scout
+
+    # Search the contents of the files with ripgrep as the query changes
+    $ scout --live='rg --line-number --color=always {q}' --ansi
+
+    # Handle any file name, even with newlines
+    $ find . -print0 | scout --read0 --print0 | xargs -0 ls -l
+
+    # Move around the list with Alt-j and Alt-k
+    $ git ls-files | scout --bind=alt-j:select-down,alt-k:select-up
+
+    # Switch between all the files and the modified ones with Alt-a and Alt-m
+    $ git ls-files | scout --bind='alt-a:reload(git ls-files),alt-m:reload(git ls-files -m)'
+
+    # Recall previous queries with ^r
+    $ git ls-files | scout --history="$HOME/.scout_history"
+
+    # Rank the branches checked out often and recently higher
+    $ git branch --format='%(refname:short)' | scout --history-key=branches | xargs git checkout
+
+    # Show the contents of the selected file
+    $ git ls-files | scout --preview='cat {}'
+
     # Select a git branch and check it out with an inline menu
     $ git branch | cut -c 3- | scout -i | xargs git checkout"#;
 
@@ -67,6 +145,216 @@ fn main() {
                 .takes_value(true)
                 .help("Number of lines to display in inline mode, including prompt"),
         )
+        .arg(
+            Arg::with_name("tabstop")
+                .long("tabstop")
+                .value_name("N")
+                .takes_value(true)
+                .validator(validate_tabstop)
+                .help("Number of columns between tab stops (default: 8)"),
+        )
+        .arg(
+            Arg::with_name("truncate")
+                .long("truncate")
+                .value_name("SIDE")
+                .takes_value(true)
+                .possible_values(&["right", "left", "middle"])
+                .help("Where long lines are cut when the matches fit (default: right)"),
+        )
+        .arg(
+            Arg::with_name("multi")
+                .short("m")
+                .long("multi")
+                .help("Mark many lines with Tab and Shift-Tab and print all of them"),
+        )
+        .arg(
+            Arg::with_name("bind")
+                .long("bind")
+                .value_name("KEYS")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate_bindings)
+                .help("Bind keys to actions, e.g. alt-j:select-down,alt-k:select-up"),
+        )
+        .arg(
+            Arg::with_name("history")
+                .long("history")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Save accepted queries in the file and recall them with ^r/Alt-p and Alt-n"),
+        )
+        .arg(
+            Arg::with_name("history-size")
+                .long("history-size")
+                .value_name("N")
+                .takes_value(true)
+                .validator(validate_limit)
+                .help("Max number of queries kept in the history file (default: 1000)"),
+        )
+        .arg(
+            Arg::with_name("history-key")
+                .long("history-key")
+                .value_name("KEY")
+                .takes_value(true)
+                .help("Save the selections under the key and rank lines selected often and recently higher"),
+        )
+        .arg(
+            Arg::with_name("list-selections")
+                .long("list-selections")
+                .requires("history-key")
+                .help("Print the saved selections of --history-key with their frecency and exit"),
+        )
+        .arg(
+            Arg::with_name("prune-selections")
+                .long("prune-selections")
+                .requires("history-key")
+                .conflicts_with("list-selections")
+                .help("Remove the selections of --history-key older than 90 days and exit"),
+        )
+        .arg(
+            Arg::with_name("preview")
+                .long("preview")
+                .value_name("COMMAND")
+                .takes_value(true)
+                .help("Show the output of the command for the selected line, {} is replaced by the line"),
+        )
+        .arg(
+            Arg::with_name("preview-window")
+                .long("preview-window")
+                .value_name("POSITION")
+                .takes_value(true)
+                .possible_values(&["right", "bottom"])
+                .help("Position of the preview pane (default: right)"),
+        )
+        .arg(
+            Arg::with_name("path")
+                .short("p")
+                .long("path")
+                .help("Score lines as file paths, favouring matches in the basename"),
+        )
+        .arg(
+            Arg::with_name("case")
+                .long("case")
+                .value_name("MODE")
+                .takes_value(true)
+                .possible_values(&["smart", "ignore", "respect"])
+                .help(
+                    "Case sensitivity: smart (sensitive with uppercase letters), ignore or respect",
+                ),
+        )
+        .arg(
+            Arg::with_name("tiebreak")
+                .long("tiebreak")
+                .value_name("CRITERIA")
+                .takes_value(true)
+                .validator(validate_tiebreak)
+                .help("Sort criteria for equal scores: score, length, begin, end or index"),
+        )
+        .arg(
+            Arg::with_name("no-sort")
+                .long("no-sort")
+                .help("Keep the input order of the matches instead of sorting them"),
+        )
+        .arg(
+            Arg::with_name("delimiter")
+                .short("d")
+                .long("delimiter")
+                .value_name("STR")
+                .takes_value(true)
+                .help("Field delimiter for the --*nth options (default: AWK-style whitespace)"),
+        )
+        .arg(
+            Arg::with_name("nth")
+                .short("n")
+                .long("nth")
+                .value_name("FIELDS")
+                .takes_value(true)
+                .validator(validate_fields)
+                .help("Only match the given comma separated fields (e.g. 1,3..,-1)"),
+        )
+        .arg(
+            Arg::with_name("with-nth")
+                .long("with-nth")
+                .value_name("FIELDS")
+                .takes_value(true)
+                .validator(validate_fields)
+                .help("Only display (and match) the given fields"),
+        )
+        .arg(
+            Arg::with_name("accept-nth")
+                .long("accept-nth")
+                .value_name("FIELDS")
+                .takes_value(true)
+                .validator(validate_fields)
+                .help("Only print the given fields of the selected line"),
+        )
+        .arg(
+            Arg::with_name("keep-empty")
+                .long("keep-empty")
+                .help("Keep the empty lines of the input, which are skipped by default"),
+        )
+        .arg(
+            Arg::with_name("command")
+                .long("command")
+                .value_name("CMD")
+                .takes_value(true)
+                .help("Run the command for the input lines instead of reading STDIN"),
+        )
+        .arg(
+            Arg::with_name("live")
+                .long("live")
+                .value_name("CMD")
+                .takes_value(true)
+                .conflicts_with_all(&["filter", "command"])
+                .help("Run the command again with the query ({q}) every time it changes"),
+        )
+        .arg(
+            Arg::with_name("type")
+                .long("type")
+                .value_name("TYPE")
+                .takes_value(true)
+                .possible_values(&["f", "d", "file", "directory"])
+                .help("Only list files (f) or directories (d) when walking the current directory"),
+        )
+        .arg(
+            Arg::with_name("max-depth")
+                .long("max-depth")
+                .value_name("DEPTH")
+                .takes_value(true)
+                .validator(validate_limit)
+                .help("Max depth of the paths when walking the current directory"),
+        )
+        .arg(
+            Arg::with_name("follow")
+                .long("follow")
+                .help("Follow symbolic links when walking the current directory"),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .long("exclude")
+                .value_name("GLOB")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate_glob)
+                .help("Skip the paths that match the glob when walking the current directory"),
+        )
+        .arg(
+            Arg::with_name("read0")
+                .long("read0")
+                .help("Read input lines separated by NUL bytes instead of newlines"),
+        )
+        .arg(
+            Arg::with_name("print0")
+                .long("print0")
+                .help("Print the selected lines followed by NUL bytes instead of newlines"),
+        )
+        .arg(
+            Arg::with_name("ansi")
+                .long("ansi")
+                .help("Display the ANSI colors of the lines without matching or printing them"),
+        )
         .arg(
             Arg::with_name("search")
                 .short("s")
@@ -75,6 +363,29 @@ fn main() {
                 .takes_value(true)
                 .help("Start searching with the given query"),
         )
+        .arg(
+            Arg::with_name("filter")
+                .short("F")
+                .long("filter")
+                .value_name("QUERY")
+                .takes_value(true)
+                .help("Print the matches of the query without the interface"),
+        )
+        .arg(
+            Arg::with_name("limit")
+                .long("limit")
+                .value_name("N")
+                .takes_value(true)
+                .requires("filter")
+                .validator(validate_limit)
+                .help("Print at most N matches with --filter"),
+        )
+        .arg(
+            Arg::with_name("print-score")
+                .long("print-score")
+                .requires("filter")
+                .help("Print the score before each match with --filter"),
+        )
         .arg(
             Arg::with_name("config")
                 .short("c")
@@ -87,7 +398,15 @@ fn main() {
 
     trace!("got args: {:?}", args);
 
-    let res: Result<Option<Text>> = task::block_on(async {
+    if args.is_present("list-selections") || args.is_present("prune-selections") {
+        selections(&args);
+    }
+
+    if args.is_present("filter") {
+        filter(&args);
+    }
+
+    let res: Result<(Vec<Text>, Config)> = task::block_on(async {
         let mut configurator = Configurator::new();
 
         match args.value_of("config") {
@@ -97,7 +416,12 @@ fn main() {
 
         // PTTY = Pseudo Terminal
         let tty = ptty::file().await?;
-        let config = configurator.from_ptty(&tty).from_args(&args).build();
+        let mut config = configurator
+            .from_ptty(&tty)
+            .from_env()
+            .from_args(&args)
+            .build()?;
+        load_boosts(&mut config);
 
         trace!("generated config: {:?}", config);
 
@@ -107,7 +431,7 @@ fn main() {
 
         // Get the list of candidates to filter from the STDIN
         // This list comes most probably from a pipe
-        let stdin = io::stdin();
+        let stdin = piped_stdin();
 
         // The architecture of the app is async, one task (a future) will
         // read from the PTTY while another task will write to it. We can't
@@ -125,17 +449,187 @@ fn main() {
 
         // The main program's thread will block until the supervisor's task finishes
         // thanks to the `task::block_on` call
-        supervisor::run(config, stdin, pttyin, pttyout).await
+        let selection = supervisor::run(config.clone(), stdin, pttyin, pttyout).await?;
+
+        if let (Some(file), Some(key)) = (config.frecency.file(), config.frecency.key()) {
+            if !selection.is_empty() {
+                if let Err(e) = record(&file, &key, &selection) {
+                    error!("failed to save the selections: {}", e);
+                }
+            }
+        }
+
+        Ok((selection, config))
     });
 
     trace!("program ended with {:?}", res);
 
     match res {
-        Ok(Some(selection)) => println!("{}", selection),
-        Ok(None) => process::exit(130),
+        Ok((selection, _)) if selection.is_empty() => process::exit(130),
+        Ok((selection, config)) => {
+            if let Err(e) = print(&selection, &config) {
+                eprintln!("ERROR: {}", e);
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Print the selected lines, with the same bytes they had in the input
+fn print(selection: &[Text], config: &Config) -> std::io::Result<()> {
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    let terminator = config.terminator();
+
+    for text in selection {
+        stdout.write_all(text.output_bytes())?;
+        stdout.write_all(terminator)?;
+    }
+
+    stdout.flush()
+}
+
+/// Run the non-interactive mode and exit
+///
+/// It exits with 1 if there are no matches, like `grep`
+fn filter(args: &ArgMatches) -> ! {
+    let res: Result<usize> = task::block_on(async {
+        let mut configurator = Configurator::new();
+
+        match args.value_of("config") {
+            Some(config_path) => configurator.from_file(config_path),
+            None => configurator.from_default_file(),
+        };
+
+        let mut config = configurator.from_env().from_args(args).build()?;
+        load_boosts(&mut config);
+
+        trace!("generated config: {:?}", config);
+
+        filter::run(config, piped_stdin(), io::stdout()).await
+    });
+
+    trace!("filter ended with {:?}", res);
+
+    match res {
+        Ok(0) => process::exit(1),
+        Ok(_) => process::exit(0),
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// STDIN, unless nothing is piped to scout
+fn piped_stdin() -> Option<io::Stdin> {
+    if std::io::stdin().is_terminal() {
+        None
+    } else {
+        Some(io::stdin())
+    }
+}
+
+/// Rank the lines selected in the past with the key higher
+///
+/// Past selections only rank lines higher, so they are not worth an error.
+fn load_boosts(config: &mut Config) {
+    if let (Some(file), Some(key)) = (config.frecency.file(), config.frecency.key()) {
+        match Store::open(&file, &key) {
+            Ok(store) => Arc::make_mut(config).matcher.set_boosts(store.boosts()),
+            Err(e) => error!("failed to read the selections: {}", e),
+        };
+    }
+}
+
+/// Save the selection in the store of past selections
+fn record(file: &Path, key: &str, selection: &[Text]) -> Result<()> {
+    let mut store = Store::open(file, key)?;
+    store.record(selection);
+    store.save()
+}
+
+/// Print or prune the past selections of the key and exit
+fn selections(args: &ArgMatches) -> ! {
+    let res: Result<()> = (|| {
+        let mut configurator = Configurator::new();
+
+        match args.value_of("config") {
+            Some(config_path) => configurator.from_file(config_path),
+            None => configurator.from_default_file(),
+        };
+
+        let config = configurator.from_args(args).build()?;
+
+        trace!("generated config: {:?}", config);
+
+        let (file, key) = match (config.frecency.file(), config.frecency.key()) {
+            (Some(file), Some(key)) => (file, key),
+            _ => return Err("No file for the selections".into()),
+        };
+        let mut store = Store::open(&file, &key)?;
+
+        if args.is_present("prune-selections") {
+            let pruned = store.prune();
+            store.save()?;
+            println!("Removed {} selections", pruned);
+        } else {
+            for (frecency, entry) in store.ranking() {
+                println!("{:.2}\t{}\t{}", frecency, entry.count(), entry.line());
+            }
+        }
+
+        Ok(())
+    })();
+
+    match res {
+        Ok(_) => process::exit(0),
         Err(e) => {
             eprintln!("ERROR: {}", e);
             process::exit(1);
         }
     }
 }
+
+fn validate_limit(value: String) -> std::result::Result<(), String> {
+    value
+        .parse::<usize>()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_tabstop(value: String) -> std::result::Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("must be greater than 0".to_string()),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn validate_fields(value: String) -> std::result::Result<(), String> {
+    Fields::parse(&value, None)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_bindings(value: String) -> std::result::Result<(), String> {
+    keys::parse_bindings(&value)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_tiebreak(value: String) -> std::result::Result<(), String> {
+    Tiebreak::parse_list(&value)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_glob(value: String) -> std::result::Result<(), String> {
+    walker::exclude(".", &[value])
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
@@ -21,14 +21,34 @@
 //! * You can use the `Left` and `Right` keys to move the cursor in the prompt
 //! * `<C-e>` will go to the end of the prompt and `<C-a>` to the beginning
 //! * `<C-u>` clears the current query
+//! * `<M-b>` and `<M-f>` move the cursor a word to the left and to the right
+//! * `<C-w>` removes the word behind the cursor and `<C-k>` everything after the cursor
+//! * `<C-y>` inserts back the last removed text
+//! * `Delete` and `<C-d>` remove the character under the cursor
+//! * `<C-_>` undoes the last change to the query
 //!
 //! ### Selecting a candidate and exiting the program
 //! * `Enter` will select the current candidate
 //! * `Esc` will exit the program without making a selection
+//!
+//! ### Query history
+//! * With a history file, `<C-r>` or `<M-p>` replace the query with the previous accepted query
+//!   and `<M-n>` with the next one
+//!
+//! ### Multi-select mode
+//! * `Tab` marks (or unmarks) the current candidate and moves down, `Shift-Tab` moves up
+//! * `Enter` will select all the marked candidates
+//!
+//! ### Custom key bindings
+//!
+//! These are only the default bindings, keys are translated into actions using the
+//! `[keys]` section of the config file and the `--bind` option (see `config::keys`).
 
 use crate::common::{Prompt, Result};
+use crate::config::keys::Action;
 use crate::config::Config;
 use crate::events::Event;
+use crate::history::History;
 use async_std::io;
 use async_std::prelude::*;
 use async_std::sync::Sender;
@@ -51,6 +71,11 @@ where
     let mut query_updated: bool;
     let mut prompt: Prompt;
 
+    let mut history = History::new(&config.history);
+    if config.history.is_enabled() {
+        history.load().await?;
+    }
+
     if let Some(q) = &config.initial_query {
         prompt = q.into();
 
@@ -68,52 +93,123 @@ where
         let keys = keys(&mut buffer, num);
 
         for key in keys {
-            match key {
-                Key::Ctrl('p') | Key::Up => {
+            let action = match config.keys.action(&key) {
+                Some(action) => action,
+                None => {
+                    if let Key::Char(ch) = key {
+                        prompt.add(ch);
+                        query_updated = true;
+                    }
+
+                    continue;
+                }
+            };
+
+            match action {
+                Action::SelectUp => {
                     screen_sender.send(Event::Up).await;
                 }
-                Key::Ctrl('n') | Key::Down => {
+                Action::SelectDown => {
                     screen_sender.send(Event::Down).await;
                 }
 
-                Key::Esc | Key::Alt('\u{0}') => {
+                Action::ToggleMark if config.multi => {
+                    screen_sender.send(Event::Toggle).await;
+                }
+                Action::ToggleDown if config.multi => {
+                    screen_sender.send(Event::ToggleDown).await;
+                }
+                Action::ToggleUp if config.multi => {
+                    screen_sender.send(Event::ToggleUp).await;
+                }
+
+                Action::ScrollLeft => {
+                    screen_sender.send(Event::ScrollLeft).await;
+                }
+                Action::ScrollRight => {
+                    screen_sender.send(Event::ScrollRight).await;
+                }
+
+                Action::Reload(command) => {
+                    screen_sender.send(Event::Reload(command)).await;
+                }
+
+                Action::Abort => {
                     screen_sender.send(Event::Exit).await;
                     engine_sender.send(Event::Exit).await;
 
                     break 'event;
                 }
-                Key::Char('\n') => {
+                Action::Accept => {
+                    if config.history.is_enabled() {
+                        if let Err(e) = history.save(&prompt.as_string()).await {
+                            log::error!("failed to save the query history: {}", e);
+                        }
+                    }
+
                     screen_sender.send(Event::Done).await;
                     engine_sender.send(Event::Done).await;
 
                     break 'event;
                 }
 
-                Key::Ctrl('u') => {
+                Action::ClearQuery => {
                     prompt.clear();
                     query_updated = true;
                 }
-                Key::Backspace => {
+                Action::BackwardDeleteChar => {
                     query_updated = prompt.backspace();
                 }
-                Key::Char(ch) => {
-                    prompt.add(ch);
-                    query_updated = true;
+                Action::DeleteChar => {
+                    query_updated = prompt.delete();
+                }
+                Action::BackwardKillWord => {
+                    query_updated = prompt.kill_word_backward();
+                }
+                Action::KillLine => {
+                    query_updated = prompt.kill_to_end();
+                }
+                Action::Yank => {
+                    query_updated = prompt.yank();
+                }
+                Action::Undo => {
+                    query_updated = prompt.undo();
                 }
 
-                Key::Left => {
+                Action::BackwardChar => {
                     prompt.left();
                     screen_sender.send(Event::Search(prompt.clone())).await;
                 }
-                Key::Right => {
+                Action::ForwardChar => {
                     prompt.right();
                     screen_sender.send(Event::Search(prompt.clone())).await;
                 }
-                Key::Ctrl('a') => {
+                Action::PreviousHistory => {
+                    if let Some(query) = history.older(&prompt.as_string()) {
+                        prompt.set(&query);
+                        query_updated = true;
+                    }
+                }
+                Action::NextHistory => {
+                    if let Some(query) = history.newer() {
+                        prompt.set(&query);
+                        query_updated = true;
+                    }
+                }
+
+                Action::BackwardWord => {
+                    prompt.word_left();
+                    screen_sender.send(Event::Search(prompt.clone())).await;
+                }
+                Action::ForwardWord => {
+                    prompt.word_right();
+                    screen_sender.send(Event::Search(prompt.clone())).await;
+                }
+                Action::BeginningOfLine => {
                     prompt.cursor_at_start();
                     screen_sender.send(Event::Search(prompt.clone())).await;
                 }
-                Key::Ctrl('e') => {
+                Action::EndOfLine => {
                     prompt.cursor_at_end();
                     screen_sender.send(Event::Search(prompt.clone())).await;
                 }
@@ -133,11 +229,8 @@ where
     Ok(())
 }
 
-fn keys(buffer: &mut Vec<u8>, num: usize) -> Vec<Key> {
+fn keys(buffer: &mut [u8], num: usize) -> Vec<Key> {
     let tmp: Vec<u8> = buffer.iter().take(num).copied().collect();
 
-    tmp.keys()
-        .filter(|k| k.is_ok())
-        .map(|k| k.unwrap())
-        .collect()
+    tmp.keys().flatten().collect()
 }
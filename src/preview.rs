@@ -0,0 +1,189 @@
+//! Run the preview command for the selected candidate
+//!
+//! Every time the selection changes the screen asks for a new preview. The command runs in a
+//! shell with `{}` replaced by the candidate, and the first lines of its output are sent back
+//! to the screen.
+//!
+//! Commands can be slow, so if a new preview is requested while the current command is still
+//! running, that command is killed and only the latest request is processed.
+
+use crate::common::{Result, Text};
+use crate::config::Config;
+use crate::events::Event;
+use crate::process::{self, Group};
+use async_std::prelude::*;
+use async_std::sync::{Receiver, Sender};
+use async_std::task;
+use futures::{select, FutureExt};
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Stdio};
+
+const PLACEHOLDER: &str = "{}";
+
+/// Run the preview task
+pub async fn task(
+    config: Config,
+    mut recv: Receiver<Event>,
+    screen_sender: Sender<Event>,
+) -> Result<()> {
+    log::trace!("starting preview");
+
+    let command = match config.preview.command() {
+        Some(command) => command,
+        None => return Ok(()),
+    };
+    let limit = config.screen.height();
+
+    let mut pending: Option<Text> = None;
+
+    loop {
+        let text = match pending.take() {
+            Some(text) => text,
+            None => match recv.next().await {
+                Some(Event::Preview(text)) => text,
+                Some(Event::Done) | Some(Event::Exit) | None => break,
+                _ => continue,
+            },
+        };
+
+        log::trace!("previewing: {:?}", text.output());
+
+        let mut child = match process::spawn(&command_for(&command, &text), Stdio::null()) {
+            Ok(child) => child,
+            Err(e) => {
+                let lines = vec![format!("Error running the preview command: {}", e)];
+                let done = Event::PreviewDone((text.index(), lines));
+                screen_sender.send(done).await;
+                continue;
+            }
+        };
+        let group = Group::new(&child);
+        let reading = group.clone();
+
+        let output = task::spawn_blocking(move || read_lines(&mut child, &reading, limit));
+
+        select! {
+            lines = output.fuse() => {
+                let done = Event::PreviewDone((text.index(), lines));
+                screen_sender.send(done).await;
+            }
+            event = recv.next().fuse() => {
+                // The preview is stale, the command has to stop right away
+                log::trace!("killing stale preview command");
+                group.kill();
+
+                match event {
+                    Some(Event::Preview(text)) => pending = Some(text),
+                    Some(Event::Done) | Some(Event::Exit) | None => break,
+                    _ => (),
+                };
+            }
+        };
+    }
+
+    log::trace!("preview done");
+
+    Ok(())
+}
+
+/// Replace the placeholder in the command with the quoted candidate
+pub fn command_for(command: &str, text: &Text) -> String {
+    command.replace(PLACEHOLDER, &process::quote(text.output()))
+}
+
+/// Read up to `limit` lines of the output and stop the command
+fn read_lines(child: &mut Child, group: &Group, limit: usize) -> Vec<String> {
+    let mut lines = vec![];
+
+    if let Some(stdout) = child.stdout.take() {
+        let mut reader = BufReader::new(stdout);
+        let mut buffer = vec![];
+
+        while lines.len() < limit {
+            buffer.clear();
+            match reader.read_until(b'\n', &mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => lines.push(sanitize(&String::from_utf8_lossy(&buffer))),
+            };
+        }
+    }
+
+    group.kill();
+    let _r = group.wait(child);
+
+    lines
+}
+
+/// Remove escape sequences and control characters that would break the screen
+fn sanitize(line: &str) -> String {
+    let mut sanitized = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            // Tabs are expanded when the preview is rendered
+            '\t' => sanitized.push(ch),
+            '\x1b' => {
+                // CSI sequences end with a character in the @ to ~ range
+                if chars.peek() == Some(&'[') {
+                    chars.next();
+                    for ch in chars.by_ref() {
+                        if ('@'..='~').contains(&ch) {
+                            break;
+                        }
+                    }
+                }
+            }
+            ch if ch.is_control() => (),
+            ch => sanitized.push(ch),
+        };
+    }
+
+    sanitized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::TextBuilder;
+
+    #[test]
+    fn command_for_test() {
+        let cases = vec![
+            ("cat {}", "src/main.rs", "cat 'src/main.rs'"),
+            ("cat {} {}", "a b", "cat 'a b' 'a b'"),
+            ("echo {}", "it's", "echo 'it'\\''s'"),
+            ("date", "foo", "date"),
+        ];
+
+        for (command, line, expected) in cases {
+            let text = TextBuilder::build(line);
+
+            assert_eq!(command_for(command, &text), expected);
+        }
+    }
+
+    #[test]
+    fn sanitize_test() {
+        let cases = vec![
+            ("foo\n", "foo"),
+            ("\x1b[1;31mfoo\x1b[0m bar", "foo bar"),
+            ("a\tb", "a\tb"),
+            ("a\rb\x07", "ab"),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(sanitize(line), expected);
+        }
+    }
+
+    #[test]
+    fn read_lines_test() {
+        let text = TextBuilder::build("a'b");
+        let command = command_for("printf '%s\\n' {} 1 2 3", &text);
+        let mut child = process::spawn(&command, Stdio::null()).unwrap();
+        let group = Group::new(&child);
+
+        assert_eq!(read_lines(&mut child, &group, 3), vec!["a'b", "1", "2"]);
+    }
+}
@@ -0,0 +1,94 @@
+//! Run shell commands that can be stopped with all their children
+//!
+//! Commands (like the preview or the source of the lines) run in a shell in their own process
+//! group, so killing the group also stops the processes started by the command.
+//!
+//! A process id can be reused once its process is reaped, so the group of a command is only
+//! killed while the command hasn't been waited for.
+
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Process group of a running command
+#[derive(Clone, Debug)]
+pub struct Group {
+    pid: u32,
+    reaped: Arc<AtomicBool>,
+}
+
+impl Group {
+    pub fn new(child: &Child) -> Self {
+        Self {
+            pid: child.id(),
+            reaped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Wait for the command to exit, after which the group is never killed
+    pub fn wait(&self, child: &mut Child) -> io::Result<ExitStatus> {
+        let status = child.wait();
+        self.reaped.store(true, Ordering::SeqCst);
+
+        status
+    }
+
+    /// Kill all the processes of the group, unless the command was already reaped
+    pub fn kill(&self) {
+        if !self.reaped.load(Ordering::SeqCst) {
+            kill(self.pid);
+        }
+    }
+}
+
+/// Run the command in its own process group, so it can be killed with all its children
+pub fn spawn(command: &str, stderr: Stdio) -> io::Result<Child> {
+    Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(stderr)
+        .spawn()
+}
+
+/// Kill the process group of the given process
+fn kill(pid: u32) {
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+/// Quote a string to be used as a single shell argument
+pub fn quote(string: &str) -> String {
+    format!("'{}'", string.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    #[test]
+    fn group_kill_test() {
+        let mut child = spawn("sleep 10", Stdio::null()).unwrap();
+        let group = Group::new(&child);
+
+        group.kill();
+        let status = group.wait(&mut child).unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+
+        // The group is gone, so it's left alone
+        assert!(group.reaped.load(Ordering::SeqCst));
+        group.kill();
+    }
+
+    #[test]
+    fn quote_test() {
+        assert_eq!(quote("src/main.rs"), "'src/main.rs'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+    }
+}
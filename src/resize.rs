@@ -0,0 +1,49 @@
+//! Listen for terminal resizes
+//!
+//! The terminal sends a `SIGWINCH` signal to the program every time its window changes its
+//! size. Signals can't be handled in the async tasks, so a dedicated thread waits for them,
+//! gets the new size of the terminal and sends it to the screen to draw everything again.
+
+use crate::common::Result;
+use crate::events::Event;
+use crate::terminal_size::terminal_size;
+use async_std::sync::Sender;
+use async_std::task;
+use signal_hook::iterator::Signals;
+use signal_hook::SIGWINCH;
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::thread;
+
+/// Send the new size of the terminal to the screen on every resize
+///
+/// The returned `Signals` stops listening once it is closed.
+pub fn watch(screen_sender: Sender<Event>) -> Result<Signals> {
+    let signals = Signals::new([SIGWINCH])?;
+    let handle = signals.clone();
+
+    thread::Builder::new()
+        .name("resize".into())
+        .spawn(move || {
+            // Many resizes in a row are delivered as a single signal
+            for _ in signals.forever() {
+                match size() {
+                    Ok(size) => {
+                        log::trace!("terminal resized to {:?}", size);
+                        task::block_on(screen_sender.send(Event::Resize(size)));
+                    }
+                    Err(e) => log::error!("failed to get the terminal size: {}", e),
+                };
+            }
+        })?;
+
+    Ok(handle)
+}
+
+fn size() -> io::Result<(usize, usize)> {
+    let tty = File::open("/dev/tty")?;
+    let (cols, rows) = terminal_size(tty.as_raw_fd())?;
+
+    Ok((cols as usize, rows as usize))
+}
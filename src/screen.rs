@@ -4,20 +4,37 @@
 //! the results from the search engine. That is, it "knows" what the person sees and why they are
 //! moving, typing, etc.
 //!
-//! When the program finishes this is the task that will return the final person's selection.
+//! The screen also asks the preview task (if there is one) for a new preview every time
+//! the selection changes.
+//!
+//! When the terminal is resized the sizes of the whole interface are computed again and
+//! everything is drawn from scratch.
+//!
+//! When the program finishes this is the task that will return the final person's selection,
+//! which can be more than one candidate in multi-select mode.
 
 use crate::common::{Result, Text};
 use crate::config::Config;
 use crate::events::Event;
+use crate::source;
 use crate::state::State;
 use crate::ui::Canvas;
 use async_std::io;
 use async_std::prelude::*;
-use async_std::sync::Receiver;
+use async_std::sync::{Arc, Receiver, Sender};
 use std::time::Instant;
 
+// Graphemes moved by every scroll of the selection
+const SCROLL_STEP: isize = 8;
+
 /// Run the screen's task
-pub async fn task<W>(config: Config, outbound: W, mut recv: Receiver<Event>) -> Result<Option<Text>>
+pub async fn task<W>(
+    mut config: Config,
+    outbound: W,
+    mut recv: Receiver<Event>,
+    preview_sender: Option<Sender<Event>>,
+    source_sender: Sender<Event>,
+) -> Result<Vec<Text>>
 where
     W: io::Write + Send + Unpin + 'static,
 {
@@ -25,7 +42,8 @@ where
 
     let mut last_timestamp = Instant::now();
     let mut render: bool;
-    let mut selection = None;
+    let mut selection = vec![];
+    let mut previewed: Option<usize> = None;
 
     let mut state = State::new();
     let mut canvas = Canvas::new(&config, outbound).await?;
@@ -39,9 +57,19 @@ where
             Event::Search(prompt) => {
                 log::trace!("printing prompt: {:?}", prompt);
 
+                // The live command finds the lines of the new query
+                let rerun = config.source.is_live() && prompt.as_string() != state.query();
+                if rerun {
+                    source_sender.send(Event::Search(prompt.clone())).await;
+                }
+
                 last_timestamp = prompt.timestamp();
                 state.set_search(prompt);
                 render = true;
+
+                if rerun {
+                    state.clear_source_error();
+                }
             }
 
             Event::Flush((matches, len)) => {
@@ -53,16 +81,31 @@ where
                 render = true;
             }
 
-            Event::SearchDone((matches, len, timestamp)) => {
-                // Only if the search timestamp is the same as the last query timestamp
-                // we will update the state. This way we will drop any intermediate search
-                // and reduce the number of renders
-                if timestamp >= last_timestamp {
-                    log::trace!("printing new search results");
+            // The engine cancels the searches of old queries, but one of them might
+            // finish right before its query is replaced. Only if the search timestamp
+            // is the same as the last query timestamp we will update the state, so
+            // stale results are never rendered
+            Event::SearchDone((matches, len, timestamp)) if timestamp >= last_timestamp => {
+                log::trace!("printing new search results");
 
-                    state.set_matches((matches, len));
-                    render = true;
-                }
+                state.set_matches((matches, len));
+                render = true;
+            }
+
+            Event::SourceError(error) => {
+                log::error!("source command failed: {}", error);
+
+                state.set_source_error(error);
+                render = true;
+            }
+
+            Event::Reload(command) => {
+                let command =
+                    source::reload_command(&command, state.selection().as_ref(), &state.query());
+                source_sender.send(Event::Reload(command)).await;
+
+                state.clear_source_error();
+                render = true;
             }
 
             Event::Up => {
@@ -77,9 +120,62 @@ where
                 state.select_down();
                 render = true;
             }
+            Event::Toggle => {
+                log::trace!("toggling mark");
+
+                state.toggle_mark();
+                render = true;
+            }
+            Event::ToggleDown => {
+                log::trace!("toggling mark and moving selection down");
+
+                state.toggle_mark();
+                state.select_down();
+                render = true;
+            }
+            Event::ToggleUp => {
+                log::trace!("toggling mark and moving selection up");
+
+                state.toggle_mark();
+                state.select_up();
+                render = true;
+            }
+
+            Event::ScrollLeft => {
+                log::trace!("scrolling selection to the left");
+
+                let range = canvas.scroll_range(&state);
+                state.scroll(-SCROLL_STEP, range);
+                render = true;
+            }
+            Event::ScrollRight => {
+                log::trace!("scrolling selection to the right");
+
+                let range = canvas.scroll_range(&state);
+                state.scroll(SCROLL_STEP, range);
+                render = true;
+            }
+
+            Event::Resize((width, height)) => {
+                log::trace!("resizing screen to {}x{}", width, height);
+
+                let mut resized = (*config).clone();
+                resized.screen.set_full_size(width, height);
+                config = Arc::new(resized);
+
+                canvas.resize(&config, &state).await?;
+            }
+
+            // Only the preview of the current selection is rendered
+            Event::PreviewDone((index, lines)) if Some(index) == previewed => {
+                log::trace!("printing preview");
+
+                state.set_preview(lines);
+                render = true;
+            }
 
             Event::Done => {
-                selection = state.selection();
+                selection = state.selections();
                 break;
             }
             Event::Exit => break,
@@ -87,11 +183,32 @@ where
             _ => (),
         };
 
+        if let Some(sender) = &preview_sender {
+            let current = state.selection();
+            let index = current.as_ref().map(|text| text.index());
+
+            if index != previewed {
+                previewed = index;
+
+                match current {
+                    Some(text) => sender.send(Event::Preview(text)).await,
+                    None => {
+                        state.set_preview(vec![]);
+                        render = true;
+                    }
+                };
+            }
+        }
+
         if render {
             canvas.render(&state).await?;
         }
     }
 
+    if let Some(sender) = &preview_sender {
+        sender.send(Event::Exit).await;
+    }
+
     log::trace!("screen done");
 
     Ok(selection)
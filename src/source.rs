@@ -0,0 +1,369 @@
+//! Run a command to get the input lines
+//!
+//! When nothing is piped to scout (STDIN is a terminal) the default command, if any, runs in a
+//! shell and its output is read as if it was piped. Without a default command the paths of the
+//! current directory are the input lines (see `walker`). A command given with `--command` runs
+//! even if there is something in STDIN.
+//!
+//! The lines of the command go through the same path as the STDIN ones. If the command fails,
+//! its exit status and the last line of its error output are shown in the interface.
+//!
+//! The input lines can be replaced at any time with the lines of a new command (the `reload`
+//! action). The current source, STDIN or a command, stops right away and the engine forgets
+//! its lines before the new ones arrive.
+//!
+//! A live command (`--live`) is a searcher, like `rg` or `grep`, that gets the query (`{q}`) and
+//! finds the lines by itself. It runs again every time the query stops changing for a moment,
+//! stopping the run of the previous query if it is still going.
+
+use crate::ansi;
+use crate::common::{Result, Text};
+use crate::config::Config;
+use crate::data_input;
+use crate::events::Event;
+use crate::process::{self, Group};
+use crate::walker::{self, Walker};
+use async_std::future;
+use async_std::io as async_io;
+use async_std::prelude::*;
+use async_std::sync::{Receiver, Sender};
+use async_std::task::{self, JoinHandle};
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, ExitStatus, Stdio};
+use std::time::Duration;
+
+const SELECTION_PLACEHOLDER: &str = "{}";
+const QUERY_PLACEHOLDER: &str = "{q}";
+
+/// Directory walked when there is nothing else to read
+const WALK_ROOT: &str = ".";
+
+/// Time without changes in the query before running the live command again
+const LIVE_DELAY: Duration = Duration::from_millis(100);
+
+/// Command to run for the input lines, if any
+fn command(config: &Config, piped: bool) -> Option<String> {
+    let command = config.source.command()?;
+
+    if config.source.is_forced() || !piped {
+        Some(command)
+    } else {
+        None
+    }
+}
+
+/// Start reading the first input lines, from the command, STDIN or the current directory
+///
+/// STDIN is `None` when nothing is piped to scout. A live command gets the query.
+pub fn open<R>(
+    config: &Config,
+    stdin: Option<R>,
+    query: &str,
+    input_sender: &Sender<Event>,
+    screen_sender: &Sender<Event>,
+) -> (JoinHandle<Result<()>>, Option<Group>)
+where
+    R: async_io::Read + Unpin + Send + 'static,
+{
+    match (command(config, stdin.is_some()), stdin) {
+        (Some(command), _) if config.source.is_live() => {
+            let command = reload_command(&command, None, query);
+            start(config, command, input_sender, screen_sender)
+        }
+        (Some(command), _) => start(config, command, input_sender, screen_sender),
+        (None, Some(stdin)) => {
+            let reading = data_input::task(config.clone(), stdin, input_sender.clone());
+            (task::spawn(reading), None)
+        }
+        (None, None) => walk(config, input_sender, screen_sender),
+    }
+}
+
+/// Replace the placeholders of a reload command with the quoted selection and query
+pub fn reload_command(command: &str, selection: Option<&Text>, query: &str) -> String {
+    let selection = selection.map(|text| text.output()).unwrap_or_default();
+    let mut replaced = String::with_capacity(command.len());
+    let mut rest = command;
+
+    while let Some(start) = rest.find('{') {
+        replaced.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix(SELECTION_PLACEHOLDER) {
+            replaced.push_str(&process::quote(selection));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix(QUERY_PLACEHOLDER) {
+            replaced.push_str(&process::quote(query));
+            rest = after;
+        } else {
+            replaced.push('{');
+            rest = &rest[1..];
+        }
+    }
+    replaced.push_str(rest);
+
+    replaced
+}
+
+/// Run the source task
+pub async fn task<R>(
+    config: Config,
+    stdin: Option<R>,
+    mut recv: Receiver<Event>,
+    input_sender: Sender<Event>,
+    screen_sender: Sender<Event>,
+) -> Result<()>
+where
+    R: async_io::Read + Unpin + Send + 'static,
+{
+    log::trace!("starting source");
+
+    let live = config.source.is_live();
+    let mut query = config.initial_query.clone().unwrap_or_default();
+    let mut pending: Option<String> = None;
+
+    let (mut current, mut group) = open(&config, stdin, &query, &input_sender, &screen_sender);
+
+    loop {
+        // While the query keeps changing the live command waits, so it doesn't run for every
+        // letter typed
+        let event = if pending.is_some() {
+            match future::timeout(LIVE_DELAY, recv.next()).await {
+                Ok(event) => event,
+                Err(_) => {
+                    query = pending.take().unwrap_or_default();
+
+                    let command = config.source.command().unwrap_or_default();
+                    Some(Event::Reload(reload_command(&command, None, &query)))
+                }
+            }
+        } else {
+            recv.next().await
+        };
+
+        match event {
+            Some(Event::Search(prompt)) if live => {
+                let next = prompt.as_string();
+
+                pending = if next == query { None } else { Some(next) };
+            }
+            Some(Event::Reload(command)) => {
+                log::trace!("reloading the input lines with: {}", command);
+
+                // No line of the previous source can arrive after the engine clears them
+                stop(current, group).await;
+                input_sender.send(Event::ClearLines).await;
+
+                (current, group) = start(&config, command, &input_sender, &screen_sender);
+            }
+            Some(Event::Done) | Some(Event::Exit) | None => break,
+            _ => (),
+        };
+    }
+
+    stop(current, group).await;
+
+    log::trace!("source done");
+
+    Ok(())
+}
+
+/// Start reading the lines of the command, along with the process group of the command
+fn start(
+    config: &Config,
+    command: String,
+    input_sender: &Sender<Event>,
+    screen_sender: &Sender<Event>,
+) -> (JoinHandle<Result<()>>, Option<Group>) {
+    log::trace!("running source command: {}", command);
+
+    let input_sender = input_sender.clone();
+    let screen_sender = screen_sender.clone();
+
+    match process::spawn(&command, Stdio::piped()) {
+        Ok(child) => {
+            let group = Group::new(&child);
+            let reading = run(
+                config.clone(),
+                command,
+                child,
+                group.clone(),
+                input_sender,
+                screen_sender,
+            );
+
+            (task::spawn(reading), Some(group))
+        }
+        Err(e) => {
+            let error = format!("Error running '{}': {}", command, e);
+            (fail(error, input_sender, screen_sender), None)
+        }
+    }
+}
+
+/// Start walking the current directory for the paths
+fn walk(
+    config: &Config,
+    input_sender: &Sender<Event>,
+    screen_sender: &Sender<Event>,
+) -> (JoinHandle<Result<()>>, Option<Group>) {
+    let input_sender = input_sender.clone();
+
+    match Walker::new(WALK_ROOT, &config.walker) {
+        Ok(walker) => (task::spawn(walker::task(walker, input_sender)), None),
+        Err(e) => {
+            let error = format!("Error walking the current directory: {}", e);
+            (fail(error, input_sender, screen_sender.clone()), None)
+        }
+    }
+}
+
+/// Report why there are no input lines
+fn fail(
+    error: String,
+    input_sender: Sender<Event>,
+    screen_sender: Sender<Event>,
+) -> JoinHandle<Result<()>> {
+    task::spawn(async move {
+        screen_sender.send(Event::SourceError(error)).await;
+        input_sender.send(Event::EOF).await;
+
+        Ok(())
+    })
+}
+
+/// Stop reading the current source
+async fn stop(current: JoinHandle<Result<()>>, group: Option<Group>) {
+    // The task is cancelled before the command is killed, so it doesn't report the failure,
+    // and killing the command ends its output, so the read in progress can finish
+    let kill = async {
+        if let Some(group) = group {
+            log::trace!("killing source command");
+            group.kill();
+        }
+    };
+
+    futures::join!(current.cancel(), kill);
+}
+
+/// Read the lines of the command
+async fn run(
+    config: Config,
+    command: String,
+    mut child: Child,
+    group: Group,
+    input_sender: Sender<Event>,
+    screen_sender: Sender<Event>,
+) -> Result<()> {
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    // The error output is read while the command runs, so it never blocks on a full pipe
+    let errors = task::spawn_blocking(move || stderr.map(last_line).unwrap_or_default());
+
+    // Waiting right away reaps the command even if reading its lines is cancelled
+    let status = task::spawn_blocking(move || group.wait(&mut child));
+
+    match stdout {
+        Some(stdout) => {
+            // Not a `File`, dropping one blocks until the pending operations of the file are done
+            let stdout = smol::Async::new(stdout)?;
+            data_input::task(config.clone(), stdout, input_sender).await?;
+        }
+        None => input_sender.send(Event::EOF).await,
+    };
+
+    let status = status.await?;
+    let errors = errors.await;
+
+    log::trace!("source command finished with {}", status);
+
+    // Searchers exit with an error when nothing matches, that's not worth showing
+    let quiet = config.source.is_live() && errors.is_empty();
+
+    if !status.success() && !quiet {
+        let error = failure(&command, status, &errors);
+        screen_sender.send(Event::SourceError(error)).await;
+    }
+
+    Ok(())
+}
+
+/// Last non-empty line of the output, without escape sequences nor control characters
+fn last_line<R: Read>(output: R) -> String {
+    let mut last = String::new();
+
+    for line in BufReader::new(output)
+        .split(b'\n')
+        .map_while(|line| line.ok())
+    {
+        let (line, _) = ansi::parse(&String::from_utf8_lossy(&line));
+        let line: String = line.chars().filter(|ch| !ch.is_control()).collect();
+
+        if !line.trim().is_empty() {
+            last = line;
+        }
+    }
+
+    last
+}
+
+fn failure(command: &str, status: ExitStatus, errors: &str) -> String {
+    let reason = match (status.code(), status.signal()) {
+        (Some(code), _) => format!("'{}' exited with status {}", command, code),
+        (None, Some(signal)) => format!("'{}' was killed by signal {}", command, signal),
+        (None, None) => format!("'{}' failed", command),
+    };
+
+    if errors.is_empty() {
+        reason
+    } else {
+        format!("{}: {}", reason, errors.trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::TextBuilder;
+
+    #[test]
+    fn reload_command_test() {
+        let text = TextBuilder::build("it's");
+
+        assert_eq!(
+            reload_command("grep -l {q} {} {x}", Some(&text), "{}"),
+            "grep -l '{}' 'it'\\''s' {x}"
+        );
+        assert_eq!(reload_command("ls {}", None, ""), "ls ''");
+        assert_eq!(
+            reload_command("rg -n {q}", None, "fn main"),
+            "rg -n 'fn main'"
+        );
+    }
+
+    #[test]
+    fn last_line_test() {
+        let output: &[u8] = b"warning\nls: cannot access 'x'\x1b[0m\n\n";
+        assert_eq!(last_line(output), "ls: cannot access 'x'");
+
+        let output: &[u8] = b"";
+        assert_eq!(last_line(output), "");
+    }
+
+    #[test]
+    fn failure_test() {
+        let status = ExitStatus::from_raw(2 << 8);
+        assert_eq!(
+            failure("ls x", status, "no such file\n"),
+            "'ls x' exited with status 2: no such file"
+        );
+
+        let status = ExitStatus::from_raw(9);
+        assert_eq!(
+            failure("sleep 9", status, ""),
+            "'sleep 9' was killed by signal 9"
+        );
+    }
+}
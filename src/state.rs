@@ -1,24 +1,19 @@
 //! The state of the program including interactions (moving around), last query, search
-//! results and current selection
+//! results, current selection and marked candidates
 
 use crate::common::{Prompt, Text};
 use crate::fuzzy::Candidate;
 
 /// Possible updates done to the State
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum StateUpdate {
     /// The query has changed
     Query,
     /// Any other update
+    #[default]
     All,
 }
 
-impl Default for StateUpdate {
-    fn default() -> Self {
-        Self::All
-    }
-}
-
 /// Current state of the program
 #[derive(Debug, Clone, Default)]
 pub struct State {
@@ -26,6 +21,10 @@ pub struct State {
     matches: Vec<Candidate>,
     pool_len: usize,
     selection_idx: usize,
+    shift: isize,
+    marks: Vec<Text>,
+    preview: Vec<String>,
+    source_error: Option<String>,
     last_update: StateUpdate,
 }
 
@@ -36,6 +35,7 @@ impl State {
 
     pub fn set_search(&mut self, search: Prompt) {
         self.search = Some(search);
+        self.shift = 0;
         self.last_update = StateUpdate::Query;
     }
 
@@ -82,6 +82,7 @@ impl State {
         } else {
             self.selection_idx -= 1;
         }
+        self.shift = 0;
         self.last_update = StateUpdate::All;
     }
 
@@ -91,6 +92,7 @@ impl State {
         } else {
             self.selection_idx += 1;
         }
+        self.shift = 0;
         self.last_update = StateUpdate::All;
     }
 
@@ -98,10 +100,94 @@ impl State {
         self.selection_idx
     }
 
+    /// Move the visible part of the selection a number of graphemes
+    ///
+    /// It only changes long lines that don't fit in the screen, so the shift is kept in the
+    /// range the line can scroll. The shift goes back to zero when the query or the selection
+    /// change.
+    pub fn scroll(&mut self, graphemes: isize, (min, max): (isize, isize)) {
+        self.shift = (self.shift + graphemes).clamp(min, max);
+        self.last_update = StateUpdate::All;
+    }
+
+    /// Graphemes the visible part of the selection is moved to the right
+    pub fn shift(&self) -> isize {
+        self.shift
+    }
+
     pub fn selection(&self) -> Option<Text> {
-        match self.matches.get(self.selection_idx) {
-            Some(candidate) => Some(candidate.text.clone()),
-            None => None,
+        self.matches
+            .get(self.selection_idx)
+            .map(|candidate| candidate.text.clone())
+    }
+
+    /// Mark the current selection or unmark it if it is already marked
+    ///
+    /// Marks are kept by input line, so they don't change with new queries
+    pub fn toggle_mark(&mut self) {
+        let selection = match self.selection() {
+            Some(selection) => selection,
+            None => return,
+        };
+
+        let position = self
+            .marks
+            .iter()
+            .position(|text| text.index() == selection.index());
+
+        match position {
+            Some(position) => {
+                self.marks.remove(position);
+            }
+            None => self.marks.push(selection),
+        };
+
+        self.last_update = StateUpdate::All;
+    }
+
+    pub fn is_marked(&self, candidate: &Candidate) -> bool {
+        self.marks
+            .iter()
+            .any(|text| text.index() == candidate.index())
+    }
+
+    pub fn marks(&self) -> &Vec<Text> {
+        &self.marks
+    }
+
+    pub fn set_preview(&mut self, preview: Vec<String>) {
+        self.preview = preview;
+        self.last_update = StateUpdate::All;
+    }
+
+    /// Output lines of the preview command for the current selection
+    pub fn preview(&self) -> &Vec<String> {
+        &self.preview
+    }
+
+    pub fn set_source_error(&mut self, error: String) {
+        self.source_error = Some(error);
+        self.last_update = StateUpdate::All;
+    }
+
+    /// Forget the failure of the previous command, the lines come from a new one
+    pub fn clear_source_error(&mut self) {
+        self.source_error = None;
+        self.last_update = StateUpdate::All;
+    }
+
+    /// Why the command that produces the input lines failed, if it did
+    pub fn source_error(&self) -> Option<&str> {
+        self.source_error.as_deref()
+    }
+
+    /// All the marked candidates in the order they were marked or
+    /// the current selection if there are no marks
+    pub fn selections(&self) -> Vec<Text> {
+        if self.marks.is_empty() {
+            self.selection().into_iter().collect()
+        } else {
+            self.marks.clone()
         }
     }
 
@@ -115,3 +201,47 @@ impl State {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{TextBuilder, TextOptions};
+
+    fn candidates(lines: &[&str]) -> Vec<Candidate> {
+        let options = TextOptions::default();
+
+        lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| (&TextBuilder::build_line(line, index, &options)).into())
+            .collect()
+    }
+
+    fn strings(texts: Vec<Text>) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    #[test]
+    fn state_marks_test() {
+        let mut state = State::new();
+        let matches = candidates(&["foo", "bar", "baz"]);
+        state.set_matches((matches.clone(), 3));
+
+        assert_eq!(strings(state.selections()), vec!["foo"]);
+
+        state.select_down();
+        state.toggle_mark();
+        state.select_down();
+        state.toggle_mark();
+        assert_eq!(strings(state.selections()), vec!["bar", "baz"]);
+
+        // marks are kept when the matches change
+        state.set_matches((vec![matches[2].clone(), matches[0].clone()], 3));
+        assert!(state.is_marked(&matches[2]));
+        assert!(!state.is_marked(&matches[0]));
+
+        state.select_up();
+        state.toggle_mark();
+        assert_eq!(strings(state.selections()), vec!["bar"]);
+    }
+}
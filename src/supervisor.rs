@@ -2,11 +2,18 @@
 //!
 //! The program runs over four main tasks
 //!
-//! * `data_input::task`: Handles input lines from `STDIN`
+//! * `source::task`: Handles input lines from `STDIN` (see `data_input::task`), from a command
+//!   or from the paths of the current directory (see `walker::task`) and replaces them when the
+//!   screen asks for a reload
 //! * `person_input::task`: Handles the person's interactions with the program
 //! * `engine::task`: The search engine, it performs the actual fuzzy search
 //! * `screen::task`: How to print the program's interface
 //!
+//! Optionally, there is a fifth task, `preview::task`, that runs the preview command
+//! for the selected candidate and sends its output back to the screen.
+//!
+//! A thread (see `resize::watch`) also tells the screen when the terminal changes its size.
+//!
 //! All tasks are futures that communicate between them sending events through channels
 //! as you can see in the following diagram:
 //!
@@ -19,9 +26,9 @@
 //!        +------------>+ engine |
 //!        ^             +--------+
 //!        |
-//! +------+-----+
-//! | data_input |
-//! +------------+
+//!    +---+----+
+//!    | source |
+//!    +--------+
 //! ```
 //!
 //! The input from the person using the program is delivered to both the engine and the screen in
@@ -29,14 +36,19 @@
 //! only relevant to the screen. Others, like new queries, are relevant for both. Using these two
 //! channels also makes the screen more responsive to interactions since it doesn't have to wait
 //! for the engine to finish searching in order to update the prompt, for example.
+//!
+//! When the program finishes it returns the person's selection. It is empty if the person
+//! exits without selecting anything and it can have many candidates in multi-select mode.
 
 use crate::common::{Result, Text};
 use crate::config::Config;
-use crate::data_input;
 use crate::engine;
 use crate::events::Event;
 use crate::person_input;
+use crate::preview;
+use crate::resize;
 use crate::screen;
+use crate::source;
 use async_std::io;
 use async_std::sync::{self, Receiver, Sender};
 use async_std::task;
@@ -44,7 +56,14 @@ use async_std::task;
 const CHANNEL_SIZE: usize = 1024;
 
 /// Run the program's tasks.
-pub async fn run<R, I, W>(config: Config, stdin: R, inbox: I, outbox: W) -> Result<Option<Text>>
+///
+/// STDIN is `None` when nothing is piped to the program.
+pub async fn run<R, I, W>(
+    config: Config,
+    stdin: Option<R>,
+    inbox: I,
+    outbox: W,
+) -> Result<Vec<Text>>
 where
     R: io::Read + Send + Unpin + 'static,
     I: io::Read + Send + Unpin + 'static,
@@ -53,21 +72,55 @@ where
     // channels
     let (input_sender, input_recv) = channel();
     let (output_sender, output_recv) = channel();
+    let (source_sender, source_recv) = channel();
+
+    let preview_sender = if config.preview.is_enabled() {
+        let (preview_sender, preview_recv) = channel();
+        task::spawn(preview::task(
+            config.clone(),
+            preview_recv,
+            output_sender.clone(),
+        ));
+
+        Some(preview_sender)
+    } else {
+        None
+    };
+
+    let signals = resize::watch(output_sender.clone())?;
 
-    let screen_task = task::spawn(screen::task(config.clone(), outbox, output_recv));
+    let screen_task = task::spawn(screen::task(
+        config.clone(),
+        outbox,
+        output_recv,
+        preview_sender,
+        source_sender.clone(),
+    ));
     let person_task = task::spawn(person_input::task(
-        config,
+        config.clone(),
         inbox,
         input_sender.clone(),
         output_sender.clone(),
     ));
-    let engine_task = task::spawn(engine::task(input_recv, output_sender));
-    let data_task = task::spawn(data_input::task(stdin, input_sender));
+    let source_task = task::spawn(source::task(
+        config.clone(),
+        stdin,
+        source_recv,
+        input_sender,
+        output_sender.clone(),
+    ));
+    let engine_task = task::spawn(engine::task(config, input_recv, output_sender));
 
     let selection = screen_task.await;
 
+    // Stop listening for resizes
+    signals.close();
+
+    // Stop the source command, if it is still running
+    source_sender.send(Event::Exit).await;
+    source_task.await?;
+
     // Stop all remaining tasks
-    drop(data_task);
     drop(person_task);
     drop(engine_task);
 
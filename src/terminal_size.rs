@@ -48,10 +48,7 @@ pub fn terminal_size(fileno: c_int) -> io::Result<(u16, u16)> {
         if ioctl(fileno, tiocgwinsz(), &mut size as *mut _) == 0 {
             Ok((size.col as u16, size.row as u16))
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Unable to get the terminal size.",
-            ))
+            Err(io::Error::other("Unable to get the terminal size."))
         }
     }
 }
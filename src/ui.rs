@@ -2,6 +2,7 @@
 
 mod components;
 mod convert;
+mod width;
 
 use components::*;
 
@@ -31,8 +32,7 @@ impl Mode {
         let setup = match self {
             Self::Full => format!("{}{}", ALTERNATE_SCREEN, cursor::Goto(1, 1)),
             Self::Inline(height) => {
-                let room = std::iter::repeat("\n")
-                    .take(*height)
+                let room = std::iter::repeat_n("\n", *height)
                     .collect::<Vec<&str>>()
                     .join("");
 
@@ -57,6 +57,17 @@ impl Mode {
 
         Some(teardown)
     }
+
+    // Before drawing everything again the old interface has to be cleaned up
+    //
+    // * In full mode that means cleaning the whole screen
+    // * In inline mode that means cleaning from the prompt's line to the bottom
+    pub fn reset(&self) -> String {
+        match self {
+            Self::Full => format!("{}{}", clear::All, cursor::Goto(1, 1)),
+            Self::Inline(_) => format!("\r{}", clear::AfterCursor),
+        }
+    }
 }
 
 /// This type represents the screen and how to draw each UI element on it
@@ -67,6 +78,7 @@ pub struct Canvas<W: io::Write + Send + Unpin + 'static> {
     prompt: PromptComponent,
     gauge: GaugeComponent,
     list: ListComponent,
+    preview: Option<PreviewComponent>,
 }
 
 impl<W: io::Write + Send + Unpin + 'static> Canvas<W> {
@@ -81,6 +93,11 @@ impl<W: io::Write + Send + Unpin + 'static> Canvas<W> {
         let prompt = config.into();
         let gauge = config.into();
         let list = config.into();
+        let preview = if config.preview.is_enabled() {
+            Some(config.into())
+        } else {
+            None
+        };
 
         let mut canvas = Self {
             mode,
@@ -88,6 +105,7 @@ impl<W: io::Write + Send + Unpin + 'static> Canvas<W> {
             prompt,
             gauge,
             list,
+            preview,
         };
 
         if let Some(setup) = canvas.mode.setup() {
@@ -107,36 +125,82 @@ impl<W: io::Write + Send + Unpin + 'static> Canvas<W> {
                 let display = format!("{}\r{}", clear::CurrentLine, self.prompt.render(state));
                 self.write(&display).await?;
             }
-            _ => {
-                self.list.scroll(state);
-
-                let list_renderer = self.list.render(state);
-                let list_len = list_renderer.len();
-
-                // Only add a new line if we are going to print items
-                let gauge_separator = if list_len == 0 { "" } else { "\n" };
-
-                let display = format!(
-                    "{down}{clrl}\r{gauge}{gauge_sep}{list}{clra}{up}{clrl}\r{prompt}",
-                    clrl = clear::CurrentLine,
-                    down = cursor::Down(1),
-                    gauge = self.gauge.render(state),
-                    gauge_sep = gauge_separator,
-                    list = list_renderer,
-                    clra = clear::AfterCursor,
-                    // By going up and printing as the last element the prompt we ensure the cursor
-                    // is in the right position
-                    up = cursor::Up((list_len + 1) as u16),
-                    prompt = self.prompt.render(state),
-                );
-
-                self.write(&display).await?;
-            }
+            _ => self.render_all(state).await?,
         }
 
         Ok(())
     }
 
+    /// Compute the sizes of all the components again and draw everything from scratch
+    ///
+    /// The list keeps showing the selection, with as many candidates as it can.
+    pub async fn resize(&mut self, config: &Config, state: &State) -> Result<()> {
+        let offset = self.list.offset;
+
+        self.prompt = config.into();
+        self.gauge = config.into();
+        self.list = config.into();
+        if self.preview.is_some() {
+            self.preview = Some(config.into());
+        }
+
+        self.list.resize(offset, state);
+
+        let reset = self.mode.reset();
+        self.write(&reset).await?;
+
+        self.render_all(state).await
+    }
+
+    /// How far the selection can be scrolled to the left (negative) and to the right
+    pub fn scroll_range(&self, state: &State) -> (isize, isize) {
+        self.list.scroll_range(state)
+    }
+
+    async fn render_all(&mut self, state: &State) -> Result<()> {
+        self.list.scroll(state);
+
+        let list_renderer = self.list.render(state);
+        let list_len = list_renderer.len();
+
+        // Only add a new line if we are going to print items
+        let gauge_separator = if list_len == 0 { "" } else { "\n" };
+
+        // The preview is printed starting from the gauge's line
+        let (preview, last_row) = match &self.preview {
+            Some(preview) => {
+                let up = if list_len == 0 {
+                    String::new()
+                } else {
+                    cursor::Up(list_len as u16).to_string()
+                };
+
+                (
+                    format!("{}{}", up, preview.render(state)),
+                    preview.last_row(),
+                )
+            }
+            None => (String::new(), list_len),
+        };
+
+        let display = format!(
+            "{down}{clrl}\r{gauge}{gauge_sep}{list}{clra}{preview}{up}{clrl}\r{prompt}",
+            clrl = clear::CurrentLine,
+            down = cursor::Down(1),
+            gauge = self.gauge.render(state),
+            gauge_sep = gauge_separator,
+            list = list_renderer,
+            clra = clear::AfterCursor,
+            preview = preview,
+            // By going up and printing as the last element the prompt we ensure the cursor
+            // is in the right position
+            up = cursor::Up((last_row + 1) as u16),
+            prompt = self.prompt.render(state),
+        );
+
+        self.write(&display).await
+    }
+
     async fn write(&mut self, display: &str) -> Result<()> {
         self.writer.write_all(display.as_bytes()).await?;
         self.writer.flush().await?;
@@ -156,3 +220,14 @@ impl<W: io::Write + Send + Unpin + 'static> Drop for Canvas<W> {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_reset_test() {
+        assert_eq!(Mode::Full.reset(), "\x1b[2J\x1b[1;1H");
+        assert_eq!(Mode::Inline(10).reset(), "\r\x1b[J");
+    }
+}
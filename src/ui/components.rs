@@ -9,6 +9,8 @@
 //! This two steps process for printing is done so we only need the state information while
 //! printing and not before, which means we can use references to get the data and prevent any
 //! extra data allocation from the state to the components.
+use super::width;
+use crate::config::components::{PreviewPosition, Truncation};
 use crate::config::Config;
 use crate::fuzzy::Candidate;
 use crate::state::State;
@@ -83,14 +85,21 @@ impl<'r> fmt::Display for GaugeRenderer<'r> {
         let current = self.state.matches().len();
         let total = self.state.pool_len();
 
+        let mut gauge = format!(
+            "{}{}{}{}",
+            self.gauge.prefix, current, self.gauge.symbol, total
+        );
+
+        // The failure of the source command is shown next to the counts
+        if let Some(error) = self.state.source_error() {
+            gauge = width::fit_str(&format!("{}  {}", gauge, error), self.gauge.width, 1);
+        }
+
         write!(
             f,
-            "{}{}{}{}{}{}",
+            "{}{}{}",
             self.gauge.style.prefix(),
-            self.gauge.prefix,
-            current,
-            self.gauge.symbol,
-            total,
+            gauge,
             self.gauge.style.suffix()
         )
     }
@@ -98,6 +107,7 @@ impl<'r> fmt::Display for GaugeRenderer<'r> {
 
 #[derive(Debug)]
 pub struct GaugeComponent {
+    pub width: usize,
     pub symbol: String,
     pub prefix: String,
     pub style: Style,
@@ -106,6 +116,7 @@ pub struct GaugeComponent {
 impl From<&Config> for GaugeComponent {
     fn from(config: &Config) -> Self {
         Self {
+            width: config.screen.width(),
             style: config.gauge.style().into(),
             symbol: config.gauge.symbol(),
             prefix: config.gauge.prefix(),
@@ -154,15 +165,10 @@ pub struct ListRenderer<'r> {
 
 impl<'r> ListRenderer<'r> {
     pub fn len(&'r self) -> usize {
-        let len;
         let lines = self.list.height - 2;
         let matches_len = self.state.matches().len();
 
-        if matches_len >= self.list.offset {
-            len = matches_len - self.list.offset;
-        } else {
-            len = self.list.offset - matches_len;
-        }
+        let len = matches_len.abs_diff(self.list.offset);
 
         if len >= lines {
             lines
@@ -188,13 +194,25 @@ impl<'r> fmt::Display for ListRenderer<'r> {
         while let Some((idx, candidate)) = items.next() {
             let eol = if items.peek().is_none() { "" } else { "\n" };
 
+            let marked = self.state.is_marked(candidate);
+
             let styles = if idx == self.state.selection_idx() {
                 &self.list.selection_styles
+            } else if marked {
+                &self.list.marked_styles
             } else {
                 &self.list.candidate_styles
             };
 
-            render_item(f, candidate, styles, eol)?
+            let symbol = self.list.symbol(styles, marked);
+
+            let shift = if idx == self.state.selection_idx() {
+                self.state.shift()
+            } else {
+                0
+            };
+
+            render_item(f, candidate, styles, symbol, self.list, shift, eol)?
         }
 
         Ok(())
@@ -205,26 +223,37 @@ fn render_item(
     f: &mut fmt::Formatter<'_>,
     candidate: &Candidate,
     styles: &ItemStyles,
+    symbol: &str,
+    list: &ListComponent,
+    shift: isize,
     eol: &str,
 ) -> fmt::Result {
-    let symbol = &styles.symbol;
+    let tabstop = list.tabstop;
     let style = &styles.style;
     let style_match = &styles.style_match;
     let style_symbol = &styles.style_symbol;
 
+    let symbol = width::fit_str(symbol, styles.width, tabstop);
+    let columns = list.columns(styles, &symbol);
+
     let mut strings: Vec<ANSIString<'_>> = vec![style_symbol.paint(symbol)];
-    let mut painted: Vec<ANSIString<'_>> = candidate
-        .iter()
-        .enumerate()
-        .take(styles.width - symbol.len())
-        .map(|(index, grapheme)| {
-            if candidate.matches.contains(&index) {
-                style_match.paint(grapheme)
-            } else {
-                style.paint(grapheme)
-            }
-        })
-        .collect();
+    let mut painted: Vec<ANSIString<'_>> = width::truncate(
+        candidate.iter().map(String::as_str),
+        columns,
+        tabstop,
+        list.truncate,
+        &candidate.matches,
+        shift,
+    )
+    .into_iter()
+    .map(|(index, grapheme)| match index {
+        Some(index) if candidate.matches.contains(&index) => {
+            layer(candidate.text.style_at(index), style_match).paint(grapheme)
+        }
+        Some(index) => layer(candidate.text.style_at(index), style).paint(grapheme),
+        None => style.paint(grapheme),
+    })
+    .collect();
 
     strings.append(&mut painted);
 
@@ -233,12 +262,34 @@ fn render_item(
     write!(f, "{}{}{}", clear::CurrentLine, ANSIStrings(&strings), eol)
 }
 
+/// Style of the input line (with `--ansi`) under the given one
+///
+/// The colors of the given style replace the input ones, but the rest of the rules
+/// are added to them.
+fn layer(under: Style, over: &Style) -> Style {
+    Style {
+        foreground: over.foreground.or(under.foreground),
+        background: over.background.or(under.background),
+        is_bold: over.is_bold || under.is_bold,
+        is_dimmed: over.is_dimmed || under.is_dimmed,
+        is_italic: over.is_italic || under.is_italic,
+        is_underline: over.is_underline || under.is_underline,
+        is_blink: over.is_blink || under.is_blink,
+        is_reverse: over.is_reverse || under.is_reverse,
+        is_hidden: over.is_hidden || under.is_hidden,
+        is_strikethrough: over.is_strikethrough || under.is_strikethrough,
+    }
+}
+
 #[derive(Debug)]
 pub struct ListComponent {
     pub height: usize,
     pub offset: usize,
+    pub tabstop: usize,
+    pub truncate: Truncation,
     pub candidate_styles: ItemStyles,
     pub selection_styles: ItemStyles,
+    pub marked_styles: ItemStyles,
 }
 
 impl ListComponent {
@@ -257,13 +308,59 @@ impl ListComponent {
             self.offset -= top_position - selection;
         };
     }
+
+    /// Go back to the offset the list had before a resize
+    ///
+    /// The selection stays visible and the list shows as many candidates as fit in it.
+    pub fn resize(&mut self, offset: usize, state: &State) {
+        let len = self.height - 2;
+
+        self.offset = offset
+            .min(state.selection_idx())
+            .min(state.matches().len().saturating_sub(len));
+
+        self.scroll(state);
+    }
+
+    /// How far the selection can be scrolled, see `width::scroll_range`
+    pub fn scroll_range(&self, state: &State) -> (isize, isize) {
+        let candidate = match state.matches().get(state.selection_idx()) {
+            Some(candidate) => candidate,
+            None => return (0, 0),
+        };
+
+        let styles = &self.selection_styles;
+        let symbol = self.symbol(styles, state.is_marked(candidate));
+        let symbol = width::fit_str(symbol, styles.width, self.tabstop);
+
+        width::scroll_range(
+            candidate.iter().map(String::as_str),
+            self.columns(styles, &symbol),
+            self.tabstop,
+            self.truncate,
+            &candidate.matches,
+        )
+    }
+
+    // The selection keeps its styles, but shows that it is marked
+    fn symbol<'s>(&'s self, styles: &'s ItemStyles, marked: bool) -> &'s str {
+        if marked {
+            &self.marked_styles.symbol
+        } else {
+            &styles.symbol
+        }
+    }
+
+    // Columns left for the line after its symbol
+    fn columns(&self, styles: &ItemStyles, symbol: &str) -> usize {
+        styles.width.saturating_sub(width::width(symbol))
+    }
 }
 
 impl From<&Config> for ListComponent {
     fn from(config: &Config) -> Self {
         let offset = 0;
-        let height = config.screen.height();
-        let width = config.screen.width();
+        let (width, height) = list_size(config);
 
         let candidate_styles = ItemStyles::new(
             width,
@@ -281,11 +378,22 @@ impl From<&Config> for ListComponent {
             config.selection.style_symbol().into(),
         );
 
+        let marked_styles = ItemStyles::new(
+            width,
+            config.marked.symbol(),
+            config.marked.style().into(),
+            config.marked.style_match().into(),
+            config.marked.style_symbol().into(),
+        );
+
         Self {
             height,
             offset,
+            tabstop: config.screen.tabstop(),
+            truncate: config.screen.truncate(),
             candidate_styles,
             selection_styles,
+            marked_styles,
         }
     }
 }
@@ -295,3 +403,193 @@ impl<'r> Render<'r, ListRenderer<'r>> for ListComponent {
         ListRenderer { list: self, state }
     }
 }
+
+/// Size (width, height) of the list, which shares the screen with the preview
+fn list_size(config: &Config) -> (usize, usize) {
+    let (width, height) = config.screen.size();
+
+    if !config.preview.is_enabled() {
+        return (width, height);
+    }
+
+    match config.preview.position() {
+        PreviewPosition::Right => (width / 2, height),
+        PreviewPosition::Bottom => (width, height - preview_height(height)),
+    }
+}
+
+/// Number of lines of the bottom preview, including its border
+fn preview_height(height: usize) -> usize {
+    (height - 2) / 2
+}
+
+#[derive(Debug)]
+pub struct PreviewRenderer<'r> {
+    preview: &'r PreviewComponent,
+    state: &'r State,
+}
+
+impl<'r> fmt::Display for PreviewRenderer<'r> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let preview = self.preview;
+        let mut lines = self.state.preview().iter();
+
+        for row in 0..preview.rows {
+            let down = if row == 0 { preview.first_row } else { 1 };
+            write!(f, "{}\r", cursor::Down(down as u16))?;
+
+            if preview.column > 0 {
+                write!(f, "{}", cursor::Right(preview.column as u16))?;
+            }
+
+            write!(f, "{}", clear::UntilNewline)?;
+
+            let line = match preview.position {
+                PreviewPosition::Right => {
+                    write!(f, "{}", preview.style_border.paint("│ "))?;
+                    lines.next()
+                }
+                PreviewPosition::Bottom if row == 0 => {
+                    let border = "─".repeat(preview.width);
+                    write!(f, "{}", preview.style_border.paint(border))?;
+                    None
+                }
+                PreviewPosition::Bottom => lines.next(),
+            };
+
+            if let Some(line) = line {
+                let line = width::fit_str(line, preview.width, preview.tabstop);
+                write!(f, "{}", line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PreviewComponent {
+    pub position: PreviewPosition,
+    /// Lines between the gauge and the first line of the preview
+    pub first_row: usize,
+    /// Columns before the preview
+    pub column: usize,
+    /// Max number of columns of each line
+    pub width: usize,
+    /// Number of lines of the preview, including the border
+    pub rows: usize,
+    pub tabstop: usize,
+    pub style_border: Style,
+}
+
+impl PreviewComponent {
+    /// Lines between the gauge and the last line of the preview
+    pub fn last_row(&self) -> usize {
+        if self.rows == 0 {
+            0
+        } else {
+            self.first_row + self.rows - 1
+        }
+    }
+}
+
+impl From<&Config> for PreviewComponent {
+    fn from(config: &Config) -> Self {
+        let (width, height) = config.screen.size();
+        let (list_width, list_height) = list_size(config);
+        let position = config.preview.position();
+        let style_border = config.preview.style_border().into();
+        let tabstop = config.screen.tabstop();
+
+        match position {
+            PreviewPosition::Right => Self {
+                position,
+                first_row: 1,
+                column: list_width,
+                // the border takes 2 columns
+                width: (width - list_width).saturating_sub(2),
+                rows: height - 2,
+                tabstop,
+                style_border,
+            },
+            PreviewPosition::Bottom => Self {
+                position,
+                first_row: list_height - 1,
+                column: 0,
+                width,
+                rows: preview_height(height),
+                tabstop,
+                style_border,
+            },
+        }
+    }
+}
+
+impl<'r> Render<'r, PreviewRenderer<'r>> for PreviewComponent {
+    fn render(&'r self, state: &'r State) -> PreviewRenderer<'r> {
+        PreviewRenderer {
+            preview: self,
+            state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{TextBuilder, TextOptions};
+    use crate::config::Cfg;
+    use std::sync::Arc;
+
+    fn list_of_height(height: usize) -> ListComponent {
+        let mut config = Cfg::default();
+        config.screen.set_full_size(80, height);
+
+        (&Arc::new(config)).into()
+    }
+
+    fn state(len: usize, selection_idx: usize) -> State {
+        let options = TextOptions::default();
+        let matches = (0..len)
+            .map(|index| (&TextBuilder::build_line("line", index, &options)).into())
+            .collect();
+
+        let mut state = State::new();
+        state.set_matches((matches, len));
+        for _ in 0..selection_idx {
+            state.select_down();
+        }
+
+        state
+    }
+
+    #[test]
+    fn list_resize_shrink_test() {
+        let state = state(20, 14);
+
+        // the selection is past the 5 lines left, so the list scrolls down to it
+        let mut list = list_of_height(7);
+        list.resize(5, &state);
+        assert_eq!(list.offset, 10);
+
+        // the offset is kept while the selection is visible
+        let mut list = list_of_height(7);
+        list.resize(12, &state);
+        assert_eq!(list.offset, 12);
+    }
+
+    #[test]
+    fn list_resize_grow_test() {
+        let state = state(20, 14);
+
+        // the offset goes back to show as many lines as fit in the list
+        let mut list = list_of_height(17);
+        list.resize(8, &state);
+        assert_eq!(list.offset, 5);
+
+        // all the lines fit
+        let mut list = list_of_height(32);
+        list.resize(8, &state);
+        assert_eq!(list.offset, 0);
+    }
+}
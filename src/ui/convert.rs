@@ -3,9 +3,9 @@
 use crate::config::styling;
 use ansi_term::{Color, Style};
 
-impl Into<Color> for styling::Color {
-    fn into(self) -> Color {
-        match self {
+impl From<styling::Color> for Color {
+    fn from(val: styling::Color) -> Self {
+        match val {
             styling::Color::Black => Color::Black,
             styling::Color::Red => Color::Red,
             styling::Color::Yellow => Color::Yellow,
@@ -20,9 +20,9 @@ impl Into<Color> for styling::Color {
     }
 }
 
-impl Into<Style> for styling::Style {
-    fn into(self) -> Style {
-        self.into_iter()
+impl From<styling::Style> for Style {
+    fn from(val: styling::Style) -> Self {
+        val.into_iter()
             .fold(Style::default(), |acc, rule| match rule {
                 styling::Rule::Reset => Style::default(),
                 styling::Rule::Underline => acc.underline(),
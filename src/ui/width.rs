@@ -0,0 +1,508 @@
+//! Measure and cut text by the columns it takes in the terminal
+//!
+//! Graphemes don't take the same room in the terminal: most of them take one column, but CJK
+//! characters and emojis take two and combining marks take none. Tabs take as many columns as
+//! needed to reach the next tab stop.
+//!
+//! Newlines (in lines read with `--read0`) are displayed with a marker (`␤`) so every line
+//! takes a single row.
+//!
+//! Lines longer than the available columns would wrap and break the whole interface, so they
+//! are cut to the last grapheme that fits. The lines of the list are cut with an ellipsis
+//! (`…`) instead, choosing the part of the line with the matches (see `truncate`).
+
+use crate::config::components::Truncation;
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const TAB: &str = "\t";
+const NEWLINE_MARKER: &str = "␤";
+pub const ELLIPSIS: &str = "…";
+
+/// Number of columns the string takes in the terminal
+pub fn width(string: &str) -> usize {
+    string.graphemes(true).fold(0, |column, grapheme| {
+        column + grapheme_width(grapheme, column, 0)
+    })
+}
+
+/// Graphemes (with their index) that fit in the given columns, with the tabs expanded
+pub fn fit<'t, I>(graphemes: I, columns: usize, tabstop: usize) -> Vec<(usize, Cow<'t, str>)>
+where
+    I: IntoIterator<Item = &'t str>,
+{
+    let mut fitted = vec![];
+    let mut column = 0;
+
+    for (index, grapheme) in graphemes.into_iter().enumerate() {
+        let grapheme_width = grapheme_width(grapheme, column, tabstop);
+
+        if column + grapheme_width > columns {
+            // A tab can be cut short, but a wide grapheme can't be split
+            if grapheme == TAB && column < columns {
+                fitted.push((index, Cow::Owned(" ".repeat(columns - column))));
+            }
+
+            break;
+        }
+
+        fitted.push((index, displayed(grapheme, grapheme_width)));
+        column += grapheme_width;
+    }
+
+    fitted
+}
+
+/// Same as `fit` but for a whole string
+pub fn fit_str(string: &str, columns: usize, tabstop: usize) -> String {
+    fit(string.graphemes(true), columns, tabstop)
+        .into_iter()
+        .map(|(_, grapheme)| grapheme)
+        .collect()
+}
+
+/// Graphemes (with their index) of the visible part of the line, with the tabs expanded
+///
+/// If the line doesn't fit in the given columns the hidden parts are replaced by an
+/// ellipsis, which has no index. Where the line is cut depends on the truncation mode:
+///
+/// * `Right` keeps the start of the line
+/// * `Left` keeps the end of the line
+/// * `Middle` keeps both the start and the end of the line
+///
+/// In any case, if some of the given matches would be hidden the visible part moves
+/// around the matches. The shift moves the visible part of a line that doesn't fit a number
+/// of graphemes to the right (or to the left if it is negative) from there.
+pub fn truncate<'t, I>(
+    graphemes: I,
+    columns: usize,
+    tabstop: usize,
+    truncation: Truncation,
+    matches: &[usize],
+    shift: isize,
+) -> Vec<(Option<usize>, Cow<'t, str>)>
+where
+    I: IntoIterator<Item = &'t str>,
+{
+    let (cells, width) = cells(graphemes, tabstop);
+
+    if width <= columns {
+        return cells
+            .into_iter()
+            .enumerate()
+            .map(|(index, (grapheme, _))| (Some(index), grapheme))
+            .collect();
+    }
+
+    if columns == 0 {
+        return vec![];
+    }
+
+    let window = Window::new(&cells, columns);
+    let len = cells.len();
+
+    let preferred = preferred(&window, truncation);
+    let visible = |ranges: &[(usize, usize)], index: &usize| {
+        ranges
+            .iter()
+            .any(|(start, end)| start <= index && index < end)
+    };
+
+    let ranges = if shift == 0 && matches.iter().all(|m| visible(&preferred, m)) {
+        preferred
+    } else {
+        let (start, end) = around_matches(&window, truncation, matches);
+
+        if shift == 0 {
+            vec![(start, end)]
+        } else {
+            vec![window.from(shifted(start, shift, len))]
+        }
+    };
+
+    let mut truncated = vec![];
+    let mut hidden = false;
+
+    for (index, (grapheme, _)) in cells.into_iter().enumerate() {
+        if visible(&ranges, &index) {
+            if hidden {
+                truncated.push((None, Cow::Borrowed(ELLIPSIS)));
+                hidden = false;
+            }
+
+            truncated.push((Some(index), grapheme));
+        } else {
+            hidden = true;
+        }
+    }
+
+    if hidden {
+        truncated.push((None, Cow::Borrowed(ELLIPSIS)));
+    }
+
+    truncated
+}
+
+/// How many graphemes the visible part of a line can be shifted to the left (the negative
+/// limit) and to the right from where `truncate` shows it
+pub fn scroll_range<'t, I>(
+    graphemes: I,
+    columns: usize,
+    tabstop: usize,
+    truncation: Truncation,
+    matches: &[usize],
+) -> (isize, isize)
+where
+    I: IntoIterator<Item = &'t str>,
+{
+    let (cells, width) = cells(graphemes, tabstop);
+
+    if width <= columns || columns == 0 {
+        return (0, 0);
+    }
+
+    let window = Window::new(&cells, columns);
+    let (start, _) = around_matches(&window, truncation, matches);
+
+    // Past the start of the last range there would only be fewer graphemes to see
+    let (last, _) = window.to(cells.len());
+
+    (-(start as isize), last.saturating_sub(start) as isize)
+}
+
+// Graphemes with the text to display them and their width, and the width of the whole line
+fn cells<'t, I>(graphemes: I, tabstop: usize) -> (Vec<(Cow<'t, str>, usize)>, usize)
+where
+    I: IntoIterator<Item = &'t str>,
+{
+    let mut column = 0;
+    let cells = graphemes
+        .into_iter()
+        .map(|grapheme| {
+            let grapheme_width = grapheme_width(grapheme, column, tabstop);
+            column += grapheme_width;
+
+            (displayed(grapheme, grapheme_width), grapheme_width)
+        })
+        .collect();
+
+    (cells, column)
+}
+
+fn preferred(window: &Window<'_, '_>, truncation: Truncation) -> Vec<(usize, usize)> {
+    match truncation {
+        Truncation::Right => vec![window.from(0)],
+        Truncation::Left => vec![window.to(window.cells.len())],
+        Truncation::Middle => window.middle(),
+    }
+}
+
+// Range with all the matches, the one the shift moves from
+fn around_matches(
+    window: &Window<'_, '_>,
+    truncation: Truncation,
+    matches: &[usize],
+) -> (usize, usize) {
+    match (matches.iter().min(), matches.iter().max()) {
+        (Some(first), Some(last)) => window.around(*first, last + 1, truncation),
+        _ => preferred(window, truncation)[0],
+    }
+}
+
+fn shifted(start: usize, shift: isize, len: usize) -> usize {
+    let start = start as isize + shift;
+
+    start.clamp(0, len.saturating_sub(1) as isize) as usize
+}
+
+/// Ranges of graphemes that fit in a number of columns, counting the ellipsis around them
+struct Window<'c, 't> {
+    cells: &'c [(Cow<'t, str>, usize)],
+    columns: usize,
+    ellipsis: usize,
+}
+
+impl<'c, 't> Window<'c, 't> {
+    fn new(cells: &'c [(Cow<'t, str>, usize)], columns: usize) -> Self {
+        Self {
+            cells,
+            columns,
+            ellipsis: ELLIPSIS.width(),
+        }
+    }
+
+    fn cost(&self, start: usize, end: usize) -> usize {
+        let width: usize = self.cells[start..end].iter().map(|(_, w)| w).sum();
+        let before = if start > 0 { self.ellipsis } else { 0 };
+        let after = if end < self.cells.len() {
+            self.ellipsis
+        } else {
+            0
+        };
+
+        width + before + after
+    }
+
+    fn fits(&self, start: usize, end: usize) -> bool {
+        self.cost(start, end) <= self.columns
+    }
+
+    /// Longest range starting at the given grapheme
+    fn from(&self, start: usize) -> (usize, usize) {
+        let mut end = start;
+        while end < self.cells.len() && self.fits(start, end + 1) {
+            end += 1;
+        }
+
+        (start, end)
+    }
+
+    /// Longest range ending at the given grapheme
+    fn to(&self, end: usize) -> (usize, usize) {
+        let mut start = end;
+        while start > 0 && self.fits(start - 1, end) {
+            start -= 1;
+        }
+
+        (start, end)
+    }
+
+    /// Start and end of the line, with the same number of columns
+    fn middle(&self) -> Vec<(usize, usize)> {
+        let len = self.cells.len();
+        let half = self.columns.saturating_sub(self.ellipsis) / 2;
+
+        let mut head = 0;
+        let mut width = 0;
+        while head < len && width + self.cells[head].1 <= half {
+            width += self.cells[head].1;
+            head += 1;
+        }
+
+        let room = self.columns.saturating_sub(self.ellipsis + width);
+        let mut tail = len;
+        width = 0;
+        while tail > head && width + self.cells[tail - 1].1 <= room {
+            width += self.cells[tail - 1].1;
+            tail -= 1;
+        }
+
+        vec![(0, head), (tail, len)]
+    }
+
+    /// Longest range with the graphemes between start and end
+    ///
+    /// If they don't fit, the range keeps the last grapheme (or the first one in left
+    /// and middle truncation)
+    fn around(&self, start: usize, end: usize, truncation: Truncation) -> (usize, usize) {
+        if !self.fits(start, end) {
+            return match truncation {
+                Truncation::Right => self.to(end),
+                _ => self.from(start),
+            };
+        }
+
+        let (mut start, mut end) = (start, end);
+        loop {
+            let mut grown = false;
+
+            if end < self.cells.len() && self.fits(start, end + 1) {
+                end += 1;
+                grown = true;
+            }
+            if start > 0 && self.fits(start - 1, end) {
+                start -= 1;
+                grown = true;
+            }
+
+            if !grown {
+                return (start, end);
+            }
+        }
+    }
+}
+
+fn grapheme_width(grapheme: &str, column: usize, tabstop: usize) -> usize {
+    if grapheme == TAB {
+        let tabstop = tabstop.max(1);
+
+        tabstop - (column % tabstop)
+    } else if is_newline(grapheme) {
+        NEWLINE_MARKER.width()
+    } else {
+        grapheme.width()
+    }
+}
+
+// What is printed for the grapheme, tabs are expanded to the given width
+fn displayed(grapheme: &str, width: usize) -> Cow<'_, str> {
+    if grapheme == TAB {
+        Cow::Owned(" ".repeat(width))
+    } else if is_newline(grapheme) {
+        Cow::Borrowed(NEWLINE_MARKER)
+    } else {
+        Cow::Borrowed(grapheme)
+    }
+}
+
+fn is_newline(grapheme: &str) -> bool {
+    grapheme == "\n" || grapheme == "\r\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_test() {
+        let cases = vec![
+            ("foo", 3),
+            ("❯ ", 2),
+            ("日本語", 6),
+            ("😀!", 3),
+            ("e\u{301}", 1),
+            ("", 0),
+        ];
+
+        for (string, expected) in cases {
+            assert_eq!(width(string), expected, "String {:?}", string);
+        }
+    }
+
+    #[test]
+    fn fit_str_test() {
+        let cases = vec![
+            ("foobar", 4, "foob"),
+            ("foo", 4, "foo"),
+            ("日本語", 5, "日本"),
+            ("日本語", 6, "日本語"),
+            ("a\tb", 8, "a   b"),
+            ("a\tb", 3, "a  "),
+            ("abcd\te", 8, "abcd    "),
+            ("ab\u{301}c", 2, "ab\u{301}"),
+            ("foo\nbar", 5, "foo␤b"),
+            ("a\r\nb", 8, "a␤b"),
+        ];
+
+        for (string, columns, expected) in cases {
+            assert_eq!(
+                fit_str(string, columns, 4),
+                expected,
+                "String {:?} in {} columns",
+                string,
+                columns
+            );
+        }
+    }
+
+    #[test]
+    fn fit_indexes_test() {
+        let fitted = fit(vec!["日", "\t", "x"], 10, 4);
+
+        let expected: Vec<(usize, Cow<'_, str>)> = vec![
+            (0, Cow::Borrowed("日")),
+            (1, Cow::Owned("  ".to_string())),
+            (2, Cow::Borrowed("x")),
+        ];
+        assert_eq!(fitted, expected);
+    }
+
+    fn truncated(
+        line: &str,
+        columns: usize,
+        truncation: Truncation,
+        matches: &[usize],
+        shift: isize,
+    ) -> String {
+        truncate(line.graphemes(true), columns, 4, truncation, matches, shift)
+            .into_iter()
+            .map(|(_, grapheme)| grapheme)
+            .collect()
+    }
+
+    #[test]
+    fn truncate_test() {
+        let cases = vec![
+            ("abc", 6, Truncation::Right, vec![], 0, "abc"),
+            ("abcdefghij", 6, Truncation::Right, vec![], 0, "abcde…"),
+            ("abcdefghij", 6, Truncation::Left, vec![], 0, "…fghij"),
+            ("abcdefghij", 6, Truncation::Middle, vec![], 0, "ab…hij"),
+            ("abcdefghij", 6, Truncation::Right, vec![1, 8], 0, "…fghi…"),
+            ("abcdefghij", 6, Truncation::Left, vec![0], 0, "abcde…"),
+            ("abcdefghij", 6, Truncation::Middle, vec![4], 0, "…defg…"),
+            ("abcdefghij", 6, Truncation::Right, vec![], 3, "…defg…"),
+            ("abcdefghij", 6, Truncation::Left, vec![], -2, "…defg…"),
+            ("abcdefghij", 6, Truncation::Right, vec![], -3, "abcde…"),
+            ("日本語テキスト", 7, Truncation::Right, vec![], 0, "日本語…"),
+            ("日本語テキスト", 7, Truncation::Left, vec![], 0, "…キスト"),
+        ];
+
+        for (line, columns, truncation, matches, shift, expected) in cases {
+            assert_eq!(
+                truncated(line, columns, truncation, &matches, shift),
+                expected,
+                "Line {:?} in {} columns with {:?} truncation, matches {:?} and shift {}",
+                line,
+                columns,
+                truncation,
+                matches,
+                shift
+            );
+        }
+    }
+
+    #[test]
+    fn scroll_range_test() {
+        let cases = vec![
+            ("abc", 6, Truncation::Right, vec![], (0, 0)),
+            ("abcdefghij", 6, Truncation::Right, vec![], (0, 5)),
+            ("abcdefghij", 6, Truncation::Left, vec![], (-5, 0)),
+            ("abcdefghij", 6, Truncation::Right, vec![1, 8], (-5, 0)),
+            ("abcdefghij", 6, Truncation::Middle, vec![4], (-3, 2)),
+        ];
+
+        for (line, columns, truncation, matches, expected) in cases {
+            let graphemes = line.graphemes(true);
+            assert_eq!(
+                scroll_range(graphemes, columns, 4, truncation, &matches),
+                expected,
+                "Line {:?} in {} columns with {:?} truncation and matches {:?}",
+                line,
+                columns,
+                truncation,
+                matches
+            );
+        }
+
+        // At the limits the whole start and end of the line are visible
+        assert_eq!(
+            truncated("abcdefghij", 6, Truncation::Right, &[], 5),
+            "…fghij"
+        );
+        assert_eq!(
+            truncated("abcdefghij", 6, Truncation::Left, &[], -5),
+            "abcde…"
+        );
+    }
+
+    #[test]
+    fn truncate_indexes_test() {
+        let truncated = truncate(
+            vec!["a", "b", "c", "d", "e"],
+            4,
+            4,
+            Truncation::Middle,
+            &[],
+            0,
+        );
+
+        let expected: Vec<(Option<usize>, Cow<'_, str>)> = vec![
+            (Some(0), Cow::Borrowed("a")),
+            (None, Cow::Borrowed(ELLIPSIS)),
+            (Some(3), Cow::Borrowed("d")),
+            (Some(4), Cow::Borrowed("e")),
+        ];
+        assert_eq!(truncated, expected);
+    }
+}
@@ -0,0 +1,183 @@
+//! Walk the current directory for the input lines
+//!
+//! When nothing is piped to scout and there is no command to run, the paths under the current
+//! directory are the input lines. The directory is walked in parallel, skipping hidden paths and
+//! the ones ignored by `.gitignore`, `.ignore` and the like, and every path is sent as soon as it
+//! is found, relative to the directory (`src/main.rs` instead of `./src/main.rs`).
+//!
+//! The walk stops when nobody reads its paths any more, like when the lines are reloaded.
+
+use crate::common::Result;
+use crate::config::components::{EntryType, WalkerConfig};
+use crate::events::Event;
+use async_std::prelude::*;
+use async_std::sync::Sender;
+use async_std::task;
+use futures::channel::mpsc;
+use futures::SinkExt;
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{DirEntry, WalkBuilder, WalkParallel, WalkState};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+const CHANNEL_SIZE: usize = 1024;
+
+/// Walk of a directory with the walker options
+pub struct Walker {
+    root: PathBuf,
+    entry_type: Option<EntryType>,
+    walk: WalkParallel,
+}
+
+impl Walker {
+    pub fn new<P: AsRef<Path>>(root: P, config: &WalkerConfig) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        let walk = WalkBuilder::new(&root)
+            .follow_links(config.follow())
+            .max_depth(config.max_depth())
+            .overrides(exclude(&root, &config.exclude())?)
+            .build_parallel();
+
+        Ok(Self {
+            root,
+            entry_type: config.entry_type(),
+            walk,
+        })
+    }
+
+    /// Send the paths found until the receiver is dropped
+    fn run(self, sender: mpsc::Sender<Vec<u8>>) {
+        let root = &self.root;
+        let entry_type = self.entry_type;
+
+        self.walk.run(|| {
+            let mut sender = sender.clone();
+
+            Box::new(move |entry| {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        log::debug!("skipping path: {}", e);
+                        return WalkState::Continue;
+                    }
+                };
+
+                if entry.depth() == 0 || !is_listed(&entry, entry_type) {
+                    return WalkState::Continue;
+                }
+
+                let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+                let path = path.as_os_str().as_bytes().to_vec();
+
+                match task::block_on(sender.send(path)) {
+                    Ok(()) => WalkState::Continue,
+                    Err(_) => WalkState::Quit,
+                }
+            })
+        });
+    }
+}
+
+/// Run the walker task
+pub async fn task(walker: Walker, sender: Sender<Event>) -> Result<()> {
+    log::trace!("starting to walk the directory");
+
+    let (paths_sender, mut paths) = mpsc::channel(CHANNEL_SIZE);
+    let _walking = task::spawn_blocking(move || walker.run(paths_sender));
+
+    while let Some(path) = paths.next().await {
+        sender.send(Event::NewLine(path)).await;
+    }
+
+    sender.send(Event::EOF).await;
+
+    log::trace!("walk done");
+
+    Ok(())
+}
+
+/// Paths that match any of the globs, to skip them
+pub fn exclude<P: AsRef<Path>>(root: P, globs: &[String]) -> Result<Override> {
+    let mut builder = OverrideBuilder::new(root);
+
+    for glob in globs {
+        builder.add(&format!("!{}", glob))?;
+    }
+
+    Ok(builder.build()?)
+}
+
+fn is_listed(entry: &DirEntry, entry_type: Option<EntryType>) -> bool {
+    match (entry_type, entry.file_type()) {
+        (Some(EntryType::File), Some(file_type)) => file_type.is_file(),
+        (Some(EntryType::Directory), Some(file_type)) => file_type.is_dir(),
+        (Some(_), None) => false,
+        (None, _) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn paths(root: &Path, config: &WalkerConfig) -> Vec<String> {
+        let walker = Walker::new(root, config).unwrap();
+        let (sender, receiver) = mpsc::channel(CHANNEL_SIZE);
+
+        walker.run(sender);
+
+        let mut paths: Vec<String> = task::block_on(receiver.collect::<Vec<_>>())
+            .into_iter()
+            .map(|path| String::from_utf8(path).unwrap())
+            .collect();
+        paths.sort();
+
+        paths
+    }
+
+    #[test]
+    fn walker_test() {
+        let root = std::env::temp_dir().join(format!("scout-walker-{}", std::process::id()));
+        fs::create_dir_all(root.join("src/ui")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+        for file in &[
+            "src/main.rs",
+            "src/ui/mod.rs",
+            "target/scout",
+            ".hidden/file",
+            "a.md",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(root.join(".ignore"), "target/\n").unwrap();
+
+        let mut config = WalkerConfig::default();
+        assert_eq!(
+            paths(&root, &config),
+            vec!["a.md", "src", "src/main.rs", "src/ui", "src/ui/mod.rs"]
+        );
+
+        config.set_entry_type(EntryType::File);
+        assert_eq!(
+            paths(&root, &config),
+            vec!["a.md", "src/main.rs", "src/ui/mod.rs"]
+        );
+
+        config.set_max_depth(2);
+        config.set_exclude(vec!["*.md".into()]);
+        assert_eq!(paths(&root, &config), vec!["src/main.rs"]);
+
+        config.set_entry_type(EntryType::Directory);
+        assert_eq!(paths(&root, &config), vec!["src", "src/ui"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn exclude_test() {
+        assert!(exclude(".", &["*.rs".into(), "target/".into()]).is_ok());
+        assert!(exclude(".", &["src/{ui".into()]).is_err());
+    }
+}
@@ -1,25 +1,46 @@
-use scout::common::{Text, TextBuilder};
+use ansi_term::Style;
+use scout::common::{Text, TextBuilder, TextOptions};
+use scout::fields::Fields;
 use scout::fuzzy::*;
+use std::collections::HashMap;
+use std::sync::Arc;
 
-fn as_pool(subjects: &Vec<&str>) -> Vec<Text> {
+fn as_pool(subjects: &[&str], options: &TextOptions) -> Vec<Text> {
     subjects
-        .into_iter()
-        .map(|s| TextBuilder::build(s))
+        .iter()
+        .enumerate()
+        .map(|(index, s)| TextBuilder::build_line(s, index, options))
         .collect()
 }
 
 fn perform_search(query: &str, cases: &Vec<&str>) -> Vec<Candidate> {
-    let pool = as_pool(cases);
+    let pool = as_pool(cases, &TextOptions::default());
 
     search(query, &pool)
 }
 
+fn perform_search_with_options(
+    query: &str,
+    cases: &Vec<&str>,
+    options: &Options,
+) -> Vec<Candidate> {
+    let pool = as_pool(cases, &TextOptions::default());
+
+    search_with_options(query, &pool, options.clone())
+}
+
 fn assert_candidate(candidate: &Candidate, expected: &str) {
     let actual = format!("{}", candidate);
 
     assert_eq!(actual, expected)
 }
 
+fn assert_candidates(results: &[Candidate], expected: &[&str]) {
+    let actual: Vec<String> = results.iter().map(|c| c.to_string()).collect();
+
+    assert_eq!(actual, expected)
+}
+
 fn assert_best_match(query: &str, cases: &Vec<&str>, expected: &str) {
     let results = perform_search(query, cases);
 
@@ -573,3 +594,333 @@ fn search_prefers_acronym_matches_that_correspond_to_the_full_candidate_acronym_
 
     assert_best_match("cs", &cases, cases[2]);
 }
+
+#[test]
+fn search_with_extended_syntax_test() {
+    let cases = vec![
+        "src/fuzzy.rs",
+        "src/fuzzy/scoring.rs",
+        "tests/fuzzy_search_test.rs",
+        "README.md",
+    ];
+
+    let results = perform_search("'src fuzzy", &cases);
+    assert_eq!(results.len(), 2);
+    assert_candidate(&results[0], cases[0]);
+    assert_candidate(&results[1], cases[1]);
+
+    let results = perform_search("^tests", &cases);
+    assert_eq!(results.len(), 1);
+    assert_candidate(&results[0], cases[2]);
+
+    let results = perform_search(".md$ | scoring.rs$", &cases);
+    assert_eq!(results.len(), 2);
+
+    let results = perform_search("rs !test", &cases);
+    assert_eq!(results.len(), 2);
+    assert_candidate(&results[0], cases[0]);
+    assert_candidate(&results[1], cases[1]);
+}
+
+#[test]
+fn search_with_extended_syntax_highlights_all_terms_test() {
+    let cases = vec!["src/fuzzy/scoring.rs"];
+
+    let results = perform_search("^src rs$", &cases);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].matches, vec![0, 1, 2, 18, 19]);
+}
+
+#[test]
+fn search_path_prefers_matches_in_the_basename_test() {
+    let cases = vec!["model/index.html", "app/views/data/model.rb"];
+    let options = Options {
+        path: true,
+        ..Default::default()
+    };
+
+    let results = perform_search("model", &cases);
+    assert_candidate(&results[0], cases[0]);
+
+    let results = perform_search_with_options("model", &cases, &options);
+    assert_candidate(&results[0], cases[1]);
+}
+
+#[test]
+fn search_path_prefers_shallow_paths_test() {
+    let cases = vec!["a/b/c/d/e/f/g/core.rs", "a/core.rs"];
+    let options = Options {
+        path: true,
+        ..Default::default()
+    };
+
+    let results = perform_search_with_options("core", &cases, &options);
+    assert_candidate(&results[0], cases[1]);
+    assert_candidate(&results[1], cases[0]);
+}
+
+#[test]
+fn search_path_uses_query_directories_test() {
+    let cases = vec!["core/app/model.rb", "app/core/model.rb"];
+    let options = Options {
+        path: true,
+        ..Default::default()
+    };
+
+    let results = perform_search_with_options("core/model", &cases, &options);
+    assert_candidate(&results[0], cases[1]);
+}
+
+#[test]
+fn search_path_keeps_full_path_matches_test() {
+    let cases = vec!["src/fuzzy/scoring.rs"];
+    let options = Options {
+        path: true,
+        ..Default::default()
+    };
+
+    let results = perform_search_with_options("fuzsc", &cases, &options);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].matches, vec![4, 5, 6, 10, 11]);
+}
+
+#[test]
+fn search_with_smart_case_test() {
+    let cases = vec!["README.md", "readme.txt", "src/ReadMe.rs"];
+    let options = Options {
+        case: Case::Smart,
+        ..Default::default()
+    };
+
+    let results = perform_search_with_options("readme", &cases, &options);
+    assert_eq!(results.len(), 3);
+
+    let results = perform_search_with_options("README", &cases, &options);
+    assert_eq!(results.len(), 1);
+    assert_candidate(&results[0], cases[0]);
+
+    let results = perform_search_with_options("RM", &cases, &options);
+    assert_eq!(results.len(), 2);
+}
+
+#[test]
+fn search_with_ignore_case_test() {
+    let cases = vec!["README.md", "readme.txt", "src/ReadMe.rs"];
+    let options = Options {
+        case: Case::Ignore,
+        ..Default::default()
+    };
+
+    let results = perform_search_with_options("README", &cases, &options);
+    assert_eq!(results.len(), 3);
+    assert_candidate(&results[0], cases[0]);
+}
+
+#[test]
+fn search_with_respect_case_test() {
+    let cases = vec!["README.md", "readme.txt", "src/ReadMe.rs"];
+    let options = Options {
+        case: Case::Respect,
+        ..Default::default()
+    };
+
+    let results = perform_search_with_options("readme", &cases, &options);
+    assert_eq!(results.len(), 1);
+    assert_candidate(&results[0], cases[1]);
+
+    let results = perform_search_with_options("^src 'Read", &cases, &options);
+    assert_eq!(results.len(), 1);
+    assert_candidate(&results[0], cases[2]);
+
+    let results = perform_search_with_options("'read", &cases, &options);
+    assert_eq!(results.len(), 1);
+    assert_candidate(&results[0], cases[1]);
+}
+
+#[test]
+fn search_only_in_selected_fields_test() {
+    let cases = vec!["a1b2c3 fix parser", "d4e5f6 parse args"];
+    let options = TextOptions {
+        nth: Some(Fields::parse("2..", None).unwrap()),
+        ..Default::default()
+    };
+    let pool = as_pool(&cases, &options);
+
+    let results = search("fix", &pool);
+    assert_eq!(results.len(), 1);
+    assert_candidate(&results[0], cases[0]);
+    assert_eq!(results[0].matches, vec![7, 8, 9]);
+
+    // the hashes are not matched
+    let results = search("a1b2", &pool);
+    assert!(results.is_empty());
+
+    // the empty query returns all lines
+    let results = search("", &pool);
+    assert_eq!(results.len(), 2);
+}
+
+#[test]
+fn search_only_in_selected_fields_with_delimiter_test() {
+    let cases = vec!["name\tvalue", "key\tname"];
+    let options = TextOptions {
+        nth: Some(Fields::parse("-1", Some("\\t")).unwrap()),
+        ..Default::default()
+    };
+    let results = search("name", &as_pool(&cases, &options));
+    assert_eq!(results.len(), 1);
+    assert_candidate(&results[0], cases[1]);
+    assert_eq!(results[0].matches, vec![4, 5, 6, 7]);
+
+    let options = TextOptions {
+        nth: Some(Fields::parse("1", Some("\\t")).unwrap()),
+        ..Default::default()
+    };
+    let results = search("^name", &as_pool(&cases, &options));
+    assert_eq!(results.len(), 1);
+    assert_candidate(&results[0], cases[0]);
+}
+
+#[test]
+fn search_displayed_fields_test() {
+    let options = TextOptions {
+        with_nth: Some(Fields::parse("2..", None).unwrap()),
+        accept_nth: Some(Fields::parse("1", None).unwrap()),
+        ..Default::default()
+    };
+    let cases = ["a1b2c3 fix parser", "d4e5f6 parse args"];
+    let pool = as_pool(&cases, &options);
+
+    let results = search("fix", &pool);
+    assert_eq!(results.len(), 1);
+    assert_candidate(&results[0], "fix parser");
+    assert_eq!(results[0].matches, vec![0, 1, 2]);
+    assert_eq!(results[0].output(), "a1b2c3");
+
+    // the hidden fields are not matched
+    let results = search("a1b2", &pool);
+    assert!(results.is_empty());
+}
+
+#[test]
+fn search_ansi_colored_lines_test() {
+    let options = TextOptions {
+        ansi: true,
+        with_nth: Some(Fields::parse("2..", None).unwrap()),
+        ..Default::default()
+    };
+    let cases = [
+        "\x1b[33ma1b2c3\x1b[m \x1b[1mfi\x1b[0mx parser",
+        "\x1b[33md4e5f6\x1b[m parse args",
+    ];
+    let pool = as_pool(&cases, &options);
+
+    // the escape sequences are not matched
+    let results = search("1mfi", &pool);
+    assert!(results.is_empty());
+
+    let results = search("fix", &pool);
+    assert_eq!(results.len(), 1);
+    assert_candidate(&results[0], "fix parser");
+    assert_eq!(results[0].matches, vec![0, 1, 2]);
+    assert_eq!(results[0].output(), "a1b2c3 fix parser");
+
+    // the colors follow the displayed fields
+    assert_eq!(results[0].text.style_at(0), Style::new().bold());
+    assert_eq!(results[0].text.style_at(2), Style::default());
+}
+
+#[test]
+fn search_keeps_input_order_for_equal_scores_test() {
+    let cases = vec!["b/foo", "a/foo", "c/foo", "d/foo"];
+
+    let results = perform_search_with_options("foo", &cases, &Options::default());
+    assert_candidates(&results, &cases);
+}
+
+#[test]
+fn search_with_tiebreak_test() {
+    let cases = vec!["xx foo", "foo xxxx", "foo"];
+
+    // the score is not the first criteria if it is part of the chain
+    let options = Options {
+        tiebreak: vec![Tiebreak::Length, Tiebreak::Score],
+        ..Default::default()
+    };
+    let results = perform_search_with_options("'foo", &cases, &options);
+    assert_candidates(&results, &["foo", "xx foo", "foo xxxx"]);
+
+    let options = Options {
+        tiebreak: vec![Tiebreak::Begin, Tiebreak::Score],
+        ..Default::default()
+    };
+    let results = perform_search_with_options("'foo", &cases, &options);
+    assert_candidates(&results, &["foo", "foo xxxx", "xx foo"]);
+
+    let options = Options {
+        tiebreak: vec![Tiebreak::End, Tiebreak::Length],
+        ..Default::default()
+    };
+    let results = perform_search_with_options("'foo", &cases, &options);
+    assert_candidates(&results, &["foo", "xx foo", "foo xxxx"]);
+
+    let cases = vec!["foo", "f_o_o", "fo"];
+    let options = Options {
+        tiebreak: vec![Tiebreak::Index, Tiebreak::Score],
+        ..Default::default()
+    };
+    let results = perform_search_with_options("fo", &cases, &options);
+    assert_candidates(&results, &["foo", "f_o_o", "fo"]);
+}
+
+#[test]
+fn search_with_only_tiebreak_length_test() {
+    let cases = vec!["foo bar baz", "foo", "foo bar"];
+
+    // the lines without the negated term have the same score, so they keep the input order
+    let results = perform_search_with_options("!zzz", &cases, &Options::default());
+    assert_candidates(&results, &cases);
+
+    // the score still goes first, but then the lines with the same score are sorted by length
+    let options = Options {
+        tiebreak: vec![Tiebreak::Length],
+        ..Default::default()
+    };
+    let results = perform_search_with_options("!zzz", &cases, &options);
+    assert_candidates(&results, &["foo", "foo bar", "foo bar baz"]);
+}
+
+#[test]
+fn search_without_sorting_test() {
+    let cases = vec!["f_o_o", "bar", "foo", "fo"];
+    let options = Options {
+        sort: false,
+        ..Default::default()
+    };
+
+    let results = perform_search_with_options("fo", &cases, &options);
+    assert_candidates(&results, &["f_o_o", "foo", "fo"]);
+}
+
+#[test]
+fn search_with_boosts_test() {
+    let cases = vec!["src/main.rs", "src/lib.rs", "README.md", "main.rs"];
+    let boosts: HashMap<String, f32> = vec![("README.md".to_string(), 10.0)].into_iter().collect();
+    let options = Options {
+        boosts: Some(Arc::new(boosts)),
+        ..Default::default()
+    };
+
+    // boosted lines go first without a query
+    let results = perform_search_with_options("", &cases, &options);
+    assert_candidates(
+        &results,
+        &["README.md", "src/main.rs", "src/lib.rs", "main.rs"],
+    );
+
+    // and rank higher with a query
+    let results = perform_search_with_options("r", &cases, &Options::default());
+    assert_ne!(results[0].to_string(), "README.md");
+    let results = perform_search_with_options("r", &cases, &options);
+    assert_candidate(&results[0], "README.md");
+}